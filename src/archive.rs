@@ -0,0 +1,193 @@
+//! Serializes dictionaries into the archived format odict reads, with the
+//! members of every set in a stable order.
+//!
+//! `Dictionary::entries` and `Etymology::senses` are `HashSet`s, which rkyv
+//! archives in iteration order, so serializing the same dictionary twice
+//! gives different bytes. The wrappers below resolve into odict's own
+//! archived types but serialize entries sorted by term and senses sorted by
+//! part of speech.
+
+use std::hash::{Hash, Hasher};
+
+use odict::{
+    ArchivedDictionary, ArchivedEntry, ArchivedEtymology, Dictionary, Entry, Etymology, ID,
+    MediaURL, Pronunciation, Sense,
+};
+use rkyv::{
+    Archive, Place, Resolver, Serialize,
+    collections::swiss_table::{ArchivedHashSet, HashSetResolver},
+    munge::munge,
+    rancor::{Fallible, Source},
+    ser::{Allocator, Writer},
+    vec::{ArchivedVec, VecResolver},
+    with::{ArchiveWith, AsBox, MapNiche, SerializeWith},
+};
+
+type Niche = MapNiche<AsBox>;
+
+/// The load factor rkyv uses for archived `HashSet`s.
+const LOAD_FACTOR: (usize, usize) = (7, 8);
+
+/// Serializes `dictionary` like `Dictionary::serialize`, but with the same
+/// bytes every time for the same dictionary.
+pub fn to_bytes(dictionary: &Dictionary) -> anyhow::Result<Vec<u8>> {
+    let bytes = rkyv::to_bytes::<rkyv::rancor::Error>(&Sorted(dictionary))?;
+
+    Ok(bytes.to_vec())
+}
+
+struct Sorted<'a, T>(&'a T);
+
+impl<T: Hash> Hash for Sorted<'_, T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+impl<T: PartialEq> PartialEq for Sorted<'_, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<T: Eq> Eq for Sorted<'_, T> {}
+
+struct DictionaryResolver {
+    id: Resolver<ID>,
+    name: <Niche as ArchiveWith<Option<String>>>::Resolver,
+    entries: HashSetResolver,
+}
+
+impl Archive for Sorted<'_, Dictionary> {
+    type Archived = ArchivedDictionary;
+    type Resolver = DictionaryResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedDictionary { id, name, entries } = out);
+
+        self.0.id.resolve(resolver.id, id);
+        Niche::resolve_with(&self.0.name, resolver.name, name);
+        ArchivedHashSet::resolve_from_len(
+            self.0.entries.len(),
+            LOAD_FACTOR,
+            resolver.entries,
+            entries,
+        );
+    }
+}
+
+impl<S> Serialize<S> for Sorted<'_, Dictionary>
+where
+    S: Fallible + Allocator + Writer + ?Sized,
+    S::Error: Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        let mut entries: Vec<_> = self.0.entries.iter().map(Sorted).collect();
+
+        entries.sort_by(|a, b| a.0.term.cmp(&b.0.term));
+
+        Ok(DictionaryResolver {
+            id: self.0.id.serialize(serializer)?,
+            name: Niche::serialize_with(&self.0.name, serializer)?,
+            entries: ArchivedHashSet::<ArchivedEntry>::serialize_from_iter::<_, Sorted<Entry>, _>(
+                entries.iter(),
+                LOAD_FACTOR,
+                serializer,
+            )?,
+        })
+    }
+}
+
+struct EntryResolver {
+    term: Resolver<String>,
+    rank: <Niche as ArchiveWith<Option<u32>>>::Resolver,
+    see_also: <Niche as ArchiveWith<Option<odict::EntryRef>>>::Resolver,
+    etymologies: VecResolver,
+    media: Resolver<Vec<MediaURL>>,
+}
+
+impl Archive for Sorted<'_, Entry> {
+    type Archived = ArchivedEntry;
+    type Resolver = EntryResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedEntry { term, rank, see_also, etymologies, media } = out);
+
+        self.0.term.resolve(resolver.term, term);
+        Niche::resolve_with(&self.0.rank, resolver.rank, rank);
+        Niche::resolve_with(&self.0.see_also, resolver.see_also, see_also);
+        ArchivedVec::resolve_from_len(self.0.etymologies.len(), resolver.etymologies, etymologies);
+        self.0.media.resolve(resolver.media, media);
+    }
+}
+
+impl<S> Serialize<S> for Sorted<'_, Entry>
+where
+    S: Fallible + Allocator + Writer + ?Sized,
+    S::Error: Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        Ok(EntryResolver {
+            term: self.0.term.serialize(serializer)?,
+            rank: Niche::serialize_with(&self.0.rank, serializer)?,
+            see_also: Niche::serialize_with(&self.0.see_also, serializer)?,
+            etymologies: ArchivedVec::<ArchivedEtymology>::serialize_from_iter::<
+                Sorted<Etymology>,
+                _,
+                _,
+            >(self.0.etymologies.iter().map(Sorted), serializer)?,
+            media: self.0.media.serialize(serializer)?,
+        })
+    }
+}
+
+struct EtymologyResolver {
+    id: <Niche as ArchiveWith<Option<String>>>::Resolver,
+    pronunciations: Resolver<Vec<Pronunciation>>,
+    description: <Niche as ArchiveWith<Option<String>>>::Resolver,
+    senses: HashSetResolver,
+}
+
+impl Archive for Sorted<'_, Etymology> {
+    type Archived = ArchivedEtymology;
+    type Resolver = EtymologyResolver;
+
+    fn resolve(&self, resolver: Self::Resolver, out: Place<Self::Archived>) {
+        munge!(let ArchivedEtymology { id, pronunciations, description, senses } = out);
+
+        Niche::resolve_with(&self.0.id, resolver.id, id);
+        self.0
+            .pronunciations
+            .resolve(resolver.pronunciations, pronunciations);
+        Niche::resolve_with(&self.0.description, resolver.description, description);
+        ArchivedHashSet::resolve_from_len(
+            self.0.senses.len(),
+            LOAD_FACTOR,
+            resolver.senses,
+            senses,
+        );
+    }
+}
+
+impl<S> Serialize<S> for Sorted<'_, Etymology>
+where
+    S: Fallible + Allocator + Writer + ?Sized,
+    S::Error: Source,
+{
+    fn serialize(&self, serializer: &mut S) -> Result<Self::Resolver, S::Error> {
+        let mut senses: Vec<&Sense> = self.0.senses.iter().collect();
+
+        senses.sort_by(|a, b| a.pos.cmp(&b.pos));
+
+        Ok(EtymologyResolver {
+            id: Niche::serialize_with(&self.0.id, serializer)?,
+            pronunciations: self.0.pronunciations.serialize(serializer)?,
+            description: Niche::serialize_with(&self.0.description, serializer)?,
+            senses: ArchivedHashSet::<odict::ArchivedSense>::serialize_from_iter::<_, Sense, _>(
+                senses.into_iter(),
+                LOAD_FACTOR,
+                serializer,
+            )?,
+        })
+    }
+}
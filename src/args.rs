@@ -1,9 +1,259 @@
-use clap::{Args, arg};
+use std::{num::NonZeroUsize, path::PathBuf, time::Duration};
 
-use crate::processors::wiktionary::SUPPORTED_LANGUAGES;
+use clap::{Args, Subcommand, ValueEnum};
+
+use odict_convert::processors::wiktionary::{Edition, Languages, WiktionaryOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Status lines and progress bars on a terminal, plain lines otherwise
+    Auto,
+    /// Status lines and progress bars
+    Tty,
+    /// One plain line per event
+    Plain,
+    /// One JSON object per event
+    Json,
+}
 
 #[derive(Debug, Args)]
 pub struct WiktionaryArgs {
-    #[arg(value_parser = SUPPORTED_LANGUAGES.keys().collect::<Vec<_>>())]
+    #[arg(help = "ISO 639-1 or 639-3 code of the language (e.g. en or eng)")]
     pub language: String,
+
+    #[arg(
+        long,
+        help = "Local kaikki JSONL(.gz) dump to use instead of downloading one"
+    )]
+    pub input: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub options: WiktionaryOptionsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WiktionaryDumpArgs {
+    #[arg(
+        help = "ISO 639-1 or 639-3 codes of the languages to build [default: every language in the dump]"
+    )]
+    pub languages: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Local copy of kaikki's all-languages JSONL(.gz) dump to use instead of downloading it"
+    )]
+    pub input: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "DIR",
+        default_value = "out/wiktionary",
+        help = "Directory to write one <language>.odict file per language to"
+    )]
+    pub output_dir: PathBuf,
+
+    #[command(flatten)]
+    pub options: WiktionaryOptionsArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct WiktionaryBilingualArgs {
+    #[arg(help = "ISO 639-1 or 639-3 code of the language to translate from (e.g. ru)")]
+    pub source: String,
+
+    #[arg(
+        help = "ISO 639-1 or 639-3 code of the language to translate into, whose Wiktionary edition's translation tables are inverted (en, fr or de)"
+    )]
+    pub target: String,
+
+    #[arg(
+        long,
+        help = "Local kaikki JSONL(.gz) extract of the target language to use instead of downloading one"
+    )]
+    pub input: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub options: WiktionaryOptionsArgs,
+}
+
+/// Options shared by the commands that build Wiktionary dictionaries.
+#[derive(Debug, Args)]
+pub struct WiktionaryOptionsArgs {
+    #[arg(
+        long,
+        default_value_t = Edition::En,
+        help = "Wiktionary edition to build from, which decides the language of the glosses (en, fr or de)"
+    )]
+    pub edition: Edition,
+
+    #[arg(
+        long,
+        help = "Skip records that can't be parsed instead of failing on the first one"
+    )]
+    pub tolerant: bool,
+
+    #[arg(
+        long,
+        requires = "tolerant",
+        value_name = "RATE",
+        help = "Fail anyway if more than this fraction of the records are rejected (e.g. 0.001)"
+    )]
+    pub max_error_rate: Option<f64>,
+
+    #[arg(
+        long,
+        requires = "tolerant",
+        value_name = "PATH",
+        help = "Where to write rejected lines [default: <output>.rejected.jsonl]"
+    )]
+    pub rejected: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Report keys the Wiktionary schema doesn't know and values of unexpected types"
+    )]
+    pub schema_audit: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Keep at most this many examples and quotations per sense"
+    )]
+    pub max_examples: Option<usize>,
+
+    #[arg(long, help = "Leave quotations out, keeping only usage examples")]
+    pub skip_quotations: bool,
+
+    #[arg(
+        long,
+        value_name = "LANGUAGES",
+        value_delimiter = ',',
+        help = "Only keep translations into these languages (e.g. fr,de) [default: all]"
+    )]
+    pub translation_languages: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Add an entry for every inflected form that isn't a headword, pointing to its lemma"
+    )]
+    pub form_redirects: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "TOML file extending or overriding the part-of-speech mapping per language"
+    )]
+    pub pos_map: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "TOML file of language profiles to apply on top of the built-in ones"
+    )]
+    pub profiles: Option<PathBuf>,
+}
+
+impl WiktionaryOptionsArgs {
+    /// The language profiles, with the ones passed with --profiles on top.
+    pub fn languages(&self) -> anyhow::Result<Languages> {
+        Languages::load(self.profiles.as_deref())
+    }
+
+    pub fn options(&self) -> WiktionaryOptions {
+        WiktionaryOptions {
+            edition: self.edition,
+            tolerant: self.tolerant,
+            max_error_rate: self.max_error_rate,
+            rejected: self.rejected.clone(),
+            schema_audit: self.schema_audit,
+            max_examples: self.max_examples,
+            skip_quotations: self.skip_quotations,
+            translation_languages: self.translation_languages.clone(),
+            form_redirects: self.form_redirects,
+            pos_map: self.pos_map.clone(),
+            profiles: self.profiles.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct CEDictArgs {
+    #[arg(
+        long,
+        help = "Local CC-CEDICT export (optionally gzipped) to use instead of downloading one"
+    )]
+    pub input: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct BuildArgs {
+    #[arg(long, help = "Manifest listing the dictionaries to build")]
+    pub manifest: PathBuf,
+
+    #[arg(
+        short,
+        long,
+        help = "How many dictionaries to build at the same time [default: the manifest's concurrency or 1]"
+    )]
+    pub jobs: Option<NonZeroUsize>,
+}
+
+/// Parses a `SOURCE=PATH` pair for `--frequency-input`.
+pub fn parse_source_input(value: &str) -> Result<(String, PathBuf), String> {
+    match value.split_once('=') {
+        Some((source, path)) if !source.is_empty() && !path.is_empty() => {
+            Ok((source.to_string(), PathBuf::from(path)))
+        }
+        _ => Err(format!("expected SOURCE=PATH, got \"{}\"", value)),
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command: CacheCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CacheCommands {
+    #[command(about = "List cached downloads with their URL, size, fetch time and users")]
+    List,
+    #[command(about = "Check every cached download against its recorded SHA-256")]
+    Verify,
+    #[command(about = "Remove partial, orphaned and corrupt downloads")]
+    Prune {
+        #[arg(
+            long,
+            value_parser = humantime::parse_duration,
+            help = "Also remove downloads that haven't been fetched for this long (e.g. 30days)"
+        )]
+        older_than: Option<Duration>,
+    },
+    #[command(about = "Remove every cached download")]
+    Clear,
+}
+
+#[derive(Debug, Args)]
+pub struct SourcesArgs {
+    #[command(subcommand)]
+    pub command: SourcesCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SourcesCommands {
+    #[command(about = "Show what kaikki.org offers for Wiktionary dictionaries")]
+    Wiktionary {
+        #[arg(
+            long,
+            required = true,
+            help = "List every language with its ISO codes, entry count and size"
+        )]
+        list: bool,
+
+        #[arg(
+            long,
+            help = "Saved copy of the kaikki.org language index to use instead of downloading it"
+        )]
+        input: Option<PathBuf>,
+    },
 }
@@ -0,0 +1,494 @@
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use reqwest::{
+    StatusCode,
+    header::{
+        CONTENT_RANGE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, IF_RANGE, LAST_MODIFIED, RANGE,
+    },
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    report::{ProgressKind, Reporter},
+    utils::hash_reader,
+};
+
+use super::{CacheMetadata, metadata::write_atomically};
+
+/// The result of a (possibly conditional) download.
+pub enum Download {
+    /// A new copy of the file was written to the cache
+    Downloaded,
+    /// The server confirmed that the cached copy is still current
+    NotModified,
+}
+
+/// Returns the path of the temporary file a download is written to before it
+/// is moved into place.
+fn partial_path(output_path: &Path) -> PathBuf {
+    output_path.with_extension("part")
+}
+
+/// The version of the file a partial download belongs to, recorded next to
+/// it so it is only resumed if the file hasn't changed since.
+#[derive(Debug, Serialize, Deserialize)]
+struct Partial {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+}
+
+impl Partial {
+    fn path_for(output_path: &Path) -> PathBuf {
+        output_path.with_extension("part.json")
+    }
+
+    fn load(output_path: &Path) -> Option<Self> {
+        let file = File::open(Self::path_for(output_path)).ok()?;
+
+        serde_json::from_reader(file).ok()
+    }
+
+    fn save(&self, output_path: &Path) -> anyhow::Result<()> {
+        write_atomically(&Self::path_for(output_path), |writer| {
+            Ok(serde_json::to_writer(writer, self)?)
+        })
+    }
+
+    /// The `If-Range` validator a request resuming this download is sent
+    /// with. Servers only compare strong ETags, so a weak one can't be used.
+    fn validator(&self) -> Option<&str> {
+        self.etag
+            .as_deref()
+            .filter(|etag| !etag.starts_with("W/"))
+            .or(self.last_modified.as_deref())
+    }
+}
+
+/// Returns how many bytes of a partial download of `url` can be resumed
+/// from, and the validator to resume them with. Partial files that can't be
+/// resumed are removed.
+fn resumable(url: &str, output_path: &Path) -> anyhow::Result<Option<(u64, String)>> {
+    let part_path = partial_path(output_path);
+
+    let Ok(part) = part_path.metadata() else {
+        return Ok(None);
+    };
+
+    let validator = Partial::load(output_path)
+        .filter(|partial| partial.url == url)
+        .and_then(|partial| partial.validator().map(str::to_string));
+
+    match validator {
+        Some(validator) if part.len() > 0 => Ok(Some((part.len(), validator))),
+        _ => {
+            discard_partial(output_path)?;
+            Ok(None)
+        }
+    }
+}
+
+fn discard_partial(output_path: &Path) -> anyhow::Result<()> {
+    for path in [partial_path(output_path), Partial::path_for(output_path)] {
+        if path.exists() {
+            std::fs::remove_file(path)?;
+        }
+    }
+
+    Ok(())
+}
+
+async fn request(
+    client: &reqwest::Client,
+    url: &str,
+    resume: Option<&(u64, String)>,
+    cached: Option<&CacheMetadata>,
+) -> anyhow::Result<reqwest::Response> {
+    let mut request = client.get(url);
+
+    // The server only sends the rest of the file if it is still the version
+    // the partial download belongs to, and the whole file otherwise
+    if let Some((resume_from, validator)) = resume {
+        request = request
+            .header(RANGE, format!("bytes={}-", resume_from))
+            .header(IF_RANGE, validator);
+    }
+
+    if let Some(metadata) = cached {
+        if let Some(etag) = &metadata.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+
+        if let Some(last_modified) = &metadata.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+    }
+
+    Ok(request.send().await?)
+}
+
+fn header(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    Some(response.headers().get(name)?.to_str().ok()?.to_string())
+}
+
+/// Returns the first byte position of a `Content-Range: bytes <start>-<end>/<total>` header.
+fn range_start(response: &reqwest::Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_RANGE)?
+        .to_str()
+        .ok()?
+        .strip_prefix("bytes ")?
+        .split('-')
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Downloads `url` to `output_path`, resuming a previously interrupted
+/// download when the server supports range requests and the file hasn't
+/// changed since.
+///
+/// The body is streamed into a `.part` file which is only renamed to
+/// `output_path` once it is complete, so the cache never contains a truncated
+/// file. The validators of the version being downloaded are kept next to the
+/// `.part` file until then. The SHA-256 of the result is recorded in the
+/// file's [`CacheMetadata`], together with the validators used to revalidate
+/// it later.
+///
+/// When `cached` is given the request is conditional on the cached copy being
+/// out of date, and nothing is downloaded if the server says it isn't.
+pub async fn download_with_progress(
+    url: &str,
+    output_path: &Path,
+    cached: Option<&CacheMetadata>,
+    reporter: &dyn Reporter,
+) -> anyhow::Result<Download> {
+    if let Some(parent) = output_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let part_path = partial_path(output_path);
+    let client = reqwest::Client::new();
+
+    // A partial file left behind by a revalidation may belong to a different
+    // version than the cached copy, so only resume initial downloads
+    let mut resume = match cached {
+        None => resumable(url, output_path)?,
+        Some(_) => {
+            discard_partial(output_path)?;
+            None
+        }
+    };
+
+    let mut response = request(&client, url, resume.as_ref(), cached).await?;
+
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(Download::NotModified);
+    }
+
+    // The partial file is at least as large as the remote file, or the
+    // server sent a different part than asked for, so start over
+    let unexpected_range = response.status() == StatusCode::PARTIAL_CONTENT
+        && range_start(&response) != resume.as_ref().map(|(resume_from, _)| *resume_from);
+
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE || unexpected_range {
+        discard_partial(output_path)?;
+        resume = None;
+        response = request(&client, url, None, cached).await?;
+    }
+
+    if !response.status().is_success() {
+        anyhow::bail!("Failed to download file: {}", response.status());
+    }
+
+    let mut hasher = Sha256::new();
+    let mut etag = header(&response, ETAG);
+    let mut last_modified = header(&response, LAST_MODIFIED);
+
+    let (file, resume_from) = match resume {
+        Some((resume_from, _)) if response.status() == StatusCode::PARTIAL_CONTENT => {
+            // Servers don't have to repeat the validators in a partial response
+            if let Some(partial) = Partial::load(output_path) {
+                etag = etag.or(partial.etag);
+                last_modified = last_modified.or(partial.last_modified);
+            }
+
+            hash_reader(&mut hasher, File::open(&part_path)?)?;
+
+            (
+                OpenOptions::new().append(true).open(&part_path)?,
+                resume_from,
+            )
+        }
+        _ => {
+            // The file changed or the server ignored the range request, so
+            // start over, remembering which version is being downloaded
+            let file = File::create(&part_path)?;
+
+            Partial {
+                url: url.to_string(),
+                etag: etag.clone(),
+                last_modified: last_modified.clone(),
+            }
+            .save(output_path)?;
+
+            (file, 0)
+        }
+    };
+
+    let total_size = resume_from + response.content_length().unwrap_or(0);
+    let progress = reporter.progress(ProgressKind::Bytes(total_size));
+
+    progress.set_position(resume_from);
+
+    let mut file = BufWriter::new(file);
+    let mut size = resume_from;
+
+    // Write chunks straight to disk and update progress bar
+    while let Some(chunk) = response.chunk().await? {
+        file.write_all(&chunk)?;
+        hasher.update(&chunk);
+        size += chunk.len() as u64;
+        progress.set_position(size);
+    }
+
+    file.flush()?;
+    file.get_ref().sync_all()?;
+
+    drop(progress);
+
+    let metadata = CacheMetadata {
+        url: url.to_string(),
+        sha256: format!("{:x}", hasher.finalize()),
+        size,
+        etag,
+        last_modified,
+        fetched_at: SystemTime::now(),
+        sources: cached.map(|c| c.sources.clone()).unwrap_or_default(),
+    };
+
+    std::fs::rename(&part_path, output_path)?;
+    discard_partial(output_path)?;
+
+    metadata.save(output_path)?;
+
+    Ok(Download::Downloaded)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::{report::Silent, utils::sha256_reader};
+
+    use super::*;
+
+    /// Starts a stand-in for a download server, which answers every request
+    /// (its lowercased head) with `respond`, and returns its URL.
+    async fn serve(respond: impl Fn(&str) -> Vec<u8> + Send + 'static) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/file.gz", listener.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut head = vec![];
+                let mut buffer = [0; 1024];
+
+                while !head.ends_with(b"\r\n\r\n") {
+                    let read = socket.read(&mut buffer).await.unwrap();
+
+                    if read == 0 {
+                        break;
+                    }
+
+                    head.extend_from_slice(&buffer[..read]);
+                }
+
+                let head = String::from_utf8_lossy(&head).to_lowercase();
+
+                socket.write_all(&respond(&head)).await.unwrap();
+                socket.shutdown().await.unwrap();
+            }
+        });
+
+        url
+    }
+
+    fn response(status: &str, headers: &[(&str, &str)], body: &str) -> Vec<u8> {
+        let mut response = format!(
+            "HTTP/1.1 {}\r\ncontent-length: {}\r\nconnection: close\r\n",
+            status,
+            body.len()
+        );
+
+        for (name, value) in headers {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+
+        format!("{}\r\n{}", response, body).into_bytes()
+    }
+
+    /// Leaves the first `len` bytes of a download of version `etag` of
+    /// `content` behind, as an interrupted download would.
+    fn interrupted(url: &str, output_path: &Path, content: &str, len: usize, etag: &str) {
+        std::fs::write(partial_path(output_path), &content[..len]).unwrap();
+
+        Partial {
+            url: url.to_string(),
+            etag: Some(etag.to_string()),
+            last_modified: None,
+        }
+        .save(output_path)
+        .unwrap();
+    }
+
+    fn assert_downloaded(output_path: &Path, content: &str, etag: &str) {
+        let metadata = CacheMetadata::load(output_path).unwrap().unwrap();
+
+        assert_eq!(std::fs::read_to_string(output_path).unwrap(), content);
+        assert_eq!(metadata.sha256, sha256_reader(content.as_bytes()).unwrap());
+        assert_eq!(metadata.size, content.len() as u64);
+        assert_eq!(metadata.etag.as_deref(), Some(etag));
+        assert!(!partial_path(output_path).exists());
+        assert!(!Partial::path_for(output_path).exists());
+    }
+
+    #[tokio::test]
+    async fn resumes_an_unchanged_file() {
+        let dir = TempDir::new("download").unwrap();
+        let output_path = dir.path().join("file");
+        let url = serve(|head| {
+            if head.contains("range: bytes=6-") && head.contains("if-range: \"v1\"") {
+                response(
+                    "206 Partial Content",
+                    &[("content-range", "bytes 6-10/11"), ("etag", "\"v1\"")],
+                    "world",
+                )
+            } else {
+                response("200 OK", &[("etag", "\"v1\"")], "hello world")
+            }
+        })
+        .await;
+
+        interrupted(&url, &output_path, "hello world", 6, "\"v1\"");
+
+        download_with_progress(&url, &output_path, None, &Silent)
+            .await
+            .unwrap();
+
+        assert_downloaded(&output_path, "hello world", "\"v1\"");
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_file_changed() {
+        let dir = TempDir::new("download").unwrap();
+        let output_path = dir.path().join("file");
+
+        // The server ignores the range because the ETag doesn't match
+        let url = serve(|_| response("200 OK", &[("etag", "\"v2\"")], "HELLO THERE")).await;
+
+        interrupted(&url, &output_path, "hello world", 6, "\"v1\"");
+
+        download_with_progress(&url, &output_path, None, &Silent)
+            .await
+            .unwrap();
+
+        assert_downloaded(&output_path, "HELLO THERE", "\"v2\"");
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_range_is_not_satisfiable() {
+        let dir = TempDir::new("download").unwrap();
+        let output_path = dir.path().join("file");
+        let url = serve(|head| {
+            if head.contains("range:") {
+                response("416 Range Not Satisfiable", &[], "")
+            } else {
+                response("200 OK", &[("etag", "\"v1\"")], "hello")
+            }
+        })
+        .await;
+
+        interrupted(&url, &output_path, "hello world", 11, "\"v1\"");
+
+        download_with_progress(&url, &output_path, None, &Silent)
+            .await
+            .unwrap();
+
+        assert_downloaded(&output_path, "hello", "\"v1\"");
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_server_sends_another_range() {
+        let dir = TempDir::new("download").unwrap();
+        let output_path = dir.path().join("file");
+        let url = serve(|head| {
+            if head.contains("range:") {
+                response(
+                    "206 Partial Content",
+                    &[("content-range", "bytes 0-4/11"), ("etag", "\"v1\"")],
+                    "hello",
+                )
+            } else {
+                response("200 OK", &[("etag", "\"v1\"")], "hello world")
+            }
+        })
+        .await;
+
+        interrupted(&url, &output_path, "hello world", 6, "\"v1\"");
+
+        download_with_progress(&url, &output_path, None, &Silent)
+            .await
+            .unwrap();
+
+        assert_downloaded(&output_path, "hello world", "\"v1\"");
+    }
+
+    #[tokio::test]
+    async fn keeps_a_cached_file_that_is_not_modified() {
+        let dir = TempDir::new("download").unwrap();
+        let output_path = dir.path().join("file");
+        let url = serve(|head| {
+            if head.contains("if-none-match: \"v1\"") {
+                response("304 Not Modified", &[("etag", "\"v1\"")], "")
+            } else {
+                response("200 OK", &[("etag", "\"v2\"")], "hello there")
+            }
+        })
+        .await;
+
+        std::fs::write(&output_path, "hello world").unwrap();
+
+        let cached = CacheMetadata {
+            url: url.clone(),
+            sha256: sha256_reader("hello world".as_bytes()).unwrap(),
+            size: 11,
+            etag: Some("\"v1\"".to_string()),
+            last_modified: None,
+            fetched_at: SystemTime::now(),
+            sources: Default::default(),
+        };
+
+        let download = download_with_progress(&url, &output_path, Some(&cached), &Silent)
+            .await
+            .unwrap();
+
+        assert!(matches!(download, Download::NotModified));
+        assert_eq!(
+            std::fs::read_to_string(&output_path).unwrap(),
+            "hello world"
+        );
+    }
+}
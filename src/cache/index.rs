@@ -0,0 +1,91 @@
+use std::path::{Path, PathBuf};
+
+use super::CacheMetadata;
+
+/// A downloaded file in the cache together with its recorded metadata.
+#[derive(Debug)]
+pub struct IndexEntry {
+    pub path: PathBuf,
+    pub metadata: CacheMetadata,
+}
+
+/// Everything found in a cache directory.
+#[derive(Debug, Default)]
+pub struct CacheIndex {
+    /// Complete downloads, sorted by URL
+    pub entries: Vec<IndexEntry>,
+    /// Partial downloads and files without a matching data or metadata file
+    pub stray: Vec<PathBuf>,
+}
+
+/// Returns whether `name` is the name of a cached file (or the SHA-256 of
+/// its URL), and so belongs to the cache.
+fn is_cache_name(name: &str) -> bool {
+    name.len() == 64
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_digit() || (b'a'..=b'f').contains(&b))
+}
+
+impl CacheIndex {
+    /// Reads the cache at `root`. Only files named after the hash of a URL
+    /// are part of the cache, anything else in the directory is left out.
+    pub fn load(root: &Path) -> anyhow::Result<Self> {
+        let mut index = Self::default();
+
+        if !root.exists() {
+            return Ok(index);
+        }
+
+        for dir_entry in std::fs::read_dir(root)? {
+            let path = dir_entry?.path();
+
+            let Some((name, extension)) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .map(|name| name.split_once('.').unwrap_or((name, "")))
+            else {
+                continue;
+            };
+
+            if !path.is_file() || !is_cache_name(name) {
+                continue;
+            }
+
+            match extension {
+                // Data files are picked up through their metadata
+                "" if !CacheMetadata::path_for(&path).exists() => index.stray.push(path),
+                "json" => {
+                    let data_path = path.with_extension("");
+
+                    match CacheMetadata::load(&data_path) {
+                        Ok(Some(metadata)) if data_path.exists() => {
+                            index.entries.push(IndexEntry {
+                                path: data_path,
+                                metadata,
+                            })
+                        }
+                        _ => index.stray.push(path),
+                    }
+                }
+                // Partial downloads, the version they belong to and metadata
+                // that was never completely written
+                "part" | "part.json" | "json.tmp" | "part.json.tmp" => index.stray.push(path),
+                // Data files with metadata, and files that aren't the cache's
+                _ => {}
+            }
+        }
+
+        index
+            .entries
+            .sort_by(|a, b| a.metadata.url.cmp(&b.metadata.url));
+        index.stray.sort();
+
+        Ok(index)
+    }
+
+    /// Returns the combined size of all complete downloads in bytes.
+    pub fn total_size(&self) -> u64 {
+        self.entries.iter().map(|e| e.metadata.size).sum()
+    }
+}
@@ -0,0 +1,76 @@
+use std::{io::Write, path::Path, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+use super::metadata::{rfc3339, write_atomically};
+use crate::error::Error;
+
+const HEADER: &str = "# This file is generated by odict-convert and records the exact sources\n\
+                      # used to build dictionaries. Build with --locked to enforce it.\n\n";
+
+/// The content a source resolved to in a build.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedSource {
+    /// The source identifier, e.g. `wiktionary/eng`
+    pub name: String,
+    /// The URL the source is downloaded from
+    pub url: String,
+    /// Hex-encoded SHA-256 of the source file
+    pub sha256: String,
+    /// Size of the source file in bytes
+    pub size: u64,
+    /// When this version of the source was retrieved
+    #[serde(with = "rfc3339")]
+    pub retrieved: SystemTime,
+}
+
+/// A `sources.lock` file, similar to `Cargo.lock` but for downloaded sources.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SourceLock {
+    #[serde(default, rename = "source")]
+    sources: Vec<LockedSource>,
+}
+
+impl SourceLock {
+    /// Loads a lockfile, returning an empty lock if it doesn't exist yet.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let lock = toml::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|e| anyhow::anyhow!("Invalid lockfile {}: {}", path.display(), e))?;
+
+        Ok(lock)
+    }
+
+    /// Writes the lockfile, replacing the old one only once the new one is
+    /// complete.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let write = || {
+            let content = format!("{}{}", HEADER, toml::to_string(self)?);
+
+            write_atomically(path, |writer| Ok(writer.write_all(content.as_bytes())?))
+        };
+
+        write().map_err(|e| {
+            Error::Write {
+                path: path.to_path_buf(),
+                source: e,
+            }
+            .into()
+        })
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedSource> {
+        self.sources.iter().find(|s| s.name == name)
+    }
+
+    /// Adds or replaces the entry for a source, keeping entries sorted by name.
+    pub fn insert(&mut self, source: LockedSource) {
+        match self.sources.binary_search_by(|s| s.name.cmp(&source.name)) {
+            Ok(i) => self.sources[i] = source,
+            Err(i) => self.sources.insert(i, source),
+        }
+    }
+}
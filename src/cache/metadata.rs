@@ -0,0 +1,96 @@
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Information recorded next to every file in the download cache.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheMetadata {
+    /// The URL the file was downloaded from
+    pub url: String,
+    /// Hex-encoded SHA-256 of the file contents
+    pub sha256: String,
+    /// Size of the file in bytes
+    pub size: u64,
+    /// The `ETag` header the server sent with the file
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// The `Last-Modified` header the server sent with the file
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    /// When the file was last downloaded or confirmed to be up to date
+    #[serde(with = "rfc3339")]
+    pub fetched_at: SystemTime,
+    /// The processors and frequency sources that use the file
+    #[serde(default)]
+    pub sources: BTreeSet<String>,
+}
+
+impl CacheMetadata {
+    /// Returns the path of the metadata file that belongs to a cached file.
+    pub fn path_for(file_path: &Path) -> PathBuf {
+        file_path.with_extension("json")
+    }
+
+    /// Reads the metadata of `file_path`, if it has any.
+    pub fn load(file_path: &Path) -> anyhow::Result<Option<Self>> {
+        let path = Self::path_for(file_path);
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let metadata = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+
+        Ok(Some(metadata))
+    }
+
+    /// Writes the metadata of `file_path`, replacing the old metadata only
+    /// once the new one is complete.
+    pub fn save(&self, file_path: &Path) -> anyhow::Result<()> {
+        write_atomically(&Self::path_for(file_path), |writer| {
+            Ok(serde_json::to_writer_pretty(writer, self)?)
+        })
+    }
+}
+
+/// Writes `path` through a temporary file next to it, which is renamed over
+/// `path` once `write` is done, so `path` is never left half-written.
+pub(super) fn write_atomically(
+    path: &Path,
+    write: impl FnOnce(&mut BufWriter<File>) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+
+    temp_path.push(".tmp");
+
+    let mut writer = BufWriter::new(File::create(&temp_path)?);
+
+    write(&mut writer)?;
+    writer.flush()?;
+    writer.get_ref().sync_all()?;
+
+    std::fs::rename(&temp_path, path)?;
+
+    Ok(())
+}
+
+pub(super) mod rfc3339 {
+    use std::time::SystemTime;
+
+    use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+    pub fn serialize<S: Serializer>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(&humantime::format_rfc3339_seconds(*time))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        humantime::parse_rfc3339(&value).map_err(D::Error::custom)
+    }
+}
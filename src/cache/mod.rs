@@ -0,0 +1,362 @@
+mod download;
+mod index;
+mod lock;
+mod metadata;
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    time::{Duration, SystemTime},
+};
+
+pub use index::{CacheIndex, IndexEntry};
+pub use lock::{LockedSource, SourceLock};
+pub use metadata::CacheMetadata;
+
+use crate::{
+    error::Error,
+    report::Reporter,
+    utils::{hash_url, sha256_reader},
+};
+
+use self::download::{Download, download_with_progress};
+
+/// Returns the default cache directory, `$XDG_CACHE_HOME/odict-convert` on Linux.
+pub fn default_root() -> PathBuf {
+    dirs::cache_dir()
+        .map(|dir| dir.join("odict-convert"))
+        .unwrap_or_else(|| PathBuf::from(".data"))
+}
+
+/// Returns the metadata of the cached copy of `url` if there is one and its
+/// contents still match the SHA-256 recorded when it was downloaded.
+///
+/// Cached files that fail verification or whose metadata can't be read are
+/// removed so they get downloaded again.
+fn verify(
+    url: &str,
+    file_path: &Path,
+    reporter: &dyn Reporter,
+) -> anyhow::Result<Option<CacheMetadata>> {
+    if !file_path.exists() {
+        return Ok(None);
+    }
+
+    let metadata = CacheMetadata::load(file_path)
+        .ok()
+        .flatten()
+        .filter(|metadata| metadata.url == url);
+
+    if let Some(metadata) = metadata
+        && sha256_reader(File::open(file_path)?)? == metadata.sha256
+    {
+        return Ok(Some(metadata));
+    }
+
+    reporter.warn(&format!(
+        "Cached copy of {} failed verification, discarding it",
+        url
+    ));
+
+    std::fs::remove_file(file_path)?;
+
+    Ok(None)
+}
+
+/// The download cache, and when cached downloads are reused or revalidated.
+#[derive(Debug)]
+pub struct Cache {
+    /// The directory downloads are cached in
+    pub root: PathBuf,
+    /// Revalidate every cached file with its server, even if it is still fresh
+    pub refresh: bool,
+    /// How long a cached file is used without revalidating it (forever if `None`)
+    pub max_age: Option<Duration>,
+    /// Never touch the network, only use cached files and local inputs
+    pub offline: bool,
+    /// Local files to use instead of downloading, keyed by source
+    pub inputs: HashMap<String, PathBuf>,
+    /// The sources used so far, merged into the lockfile that was loaded
+    pub lock: Mutex<SourceLock>,
+    /// Refuse sources whose content doesn't match `lock`
+    pub locked: bool,
+    /// The files fetched so far by URL, so each is only fetched once per run
+    pub fetched: Mutex<HashMap<String, PathBuf>>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self {
+            root: default_root(),
+            refresh: false,
+            max_age: None,
+            offline: false,
+            inputs: HashMap::new(),
+            lock: Mutex::new(SourceLock::default()),
+            locked: false,
+            fetched: Mutex::default(),
+        }
+    }
+}
+
+impl Cache {
+    /// Returns the path a download of `url` is cached at.
+    pub fn file_path(&self, url: &str) -> PathBuf {
+        self.root.join(hash_url(url))
+    }
+
+    /// Returns the SHA-256 `source` is pinned to in locked mode.
+    fn pinned(&self, source: &str) -> Option<String> {
+        if !self.locked {
+            return None;
+        }
+
+        let lock = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        lock.get(source).map(|s| s.sha256.clone())
+    }
+
+    /// Returns whether a cached file can be used without revalidating it.
+    fn is_current(&self, metadata: &CacheMetadata, pinned: Option<&str>) -> bool {
+        if let Some(sha256) = pinned {
+            return metadata.sha256 == sha256;
+        }
+
+        if self.refresh {
+            return false;
+        }
+
+        match self.max_age {
+            Some(max_age) => metadata
+                .fetched_at
+                .elapsed()
+                .is_ok_and(|age| age <= max_age),
+            None => true,
+        }
+    }
+
+    /// Checks the file used for `source` against the lock in locked mode and
+    /// records it in the lock otherwise.
+    fn record(&self, source: &str, url: &str, path: &Path, local: bool) -> anyhow::Result<()> {
+        let metadata = match local {
+            true => None,
+            false => CacheMetadata::load(path)?,
+        };
+
+        let (sha256, size, retrieved) = match metadata {
+            Some(metadata) => (metadata.sha256, metadata.size, metadata.fetched_at),
+            None => (
+                sha256_reader(File::open(path)?)?,
+                path.metadata()?.len(),
+                SystemTime::now(),
+            ),
+        };
+
+        let mut lock = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+
+        match lock.get(source) {
+            Some(locked) if locked.url == url && locked.sha256 == sha256 => {}
+            Some(locked) if self.locked => {
+                return Err(Error::Locked {
+                    message: format!(
+                        "The content of {} no longer matches the lockfile (expected {} from {}, got {} from {})",
+                        source, locked.sha256, locked.url, sha256, url
+                    ),
+                }
+                .into());
+            }
+            None if self.locked => {
+                return Err(Error::Locked {
+                    message: format!("{} ({}) is not in the lockfile", source, url),
+                }
+                .into());
+            }
+            _ => lock.insert(LockedSource {
+                name: source.to_string(),
+                url: url.to_string(),
+                sha256,
+                size,
+                retrieved,
+            }),
+        }
+
+        Ok(())
+    }
+
+    /// Returns the path of an up-to-date copy of `url`.
+    ///
+    /// A local input registered for `source` takes precedence over the cache.
+    /// URLs that were already fetched during this run are not fetched again.
+    /// Otherwise fresh cached copies are used as-is, stale ones are revalidated
+    /// with a conditional request and missing ones are downloaded. In offline
+    /// mode any cached copy is used and a missing one is an error. In locked
+    /// mode only the content pinned in the lockfile is accepted.
+    ///
+    /// `description` names the file in status messages, and `source` is
+    /// recorded in the cache index and the lockfile.
+    pub async fn fetch(
+        &self,
+        url: &str,
+        description: &str,
+        source: &str,
+        reporter: &dyn Reporter,
+    ) -> anyhow::Result<PathBuf> {
+        if let Some(input) = self.inputs.get(source) {
+            return self.use_local(input, url, description, source, reporter);
+        }
+
+        let fetched = self
+            .fetched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(url)
+            .cloned();
+
+        if let Some(file_path) = fetched {
+            return Ok(file_path);
+        }
+
+        let file_path = self
+            .fetch_cached(url, description, source, reporter)
+            .await?;
+
+        self.record(source, url, &file_path, false)?;
+
+        self.fetched
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(url.to_string(), file_path.clone());
+
+        Ok(file_path)
+    }
+
+    /// Returns `input` after checking that it exists, for sources that are
+    /// read from a local file instead of being downloaded from `url`. The
+    /// file is recorded in the lockfile like a download would be.
+    pub fn use_local(
+        &self,
+        input: &Path,
+        url: &str,
+        description: &str,
+        source: &str,
+        reporter: &dyn Reporter,
+    ) -> anyhow::Result<PathBuf> {
+        if !input.is_file() {
+            anyhow::bail!(
+                "The local {} for {} does not exist: {}",
+                description,
+                source,
+                input.display()
+            );
+        }
+
+        reporter.done(&format!(
+            "Using local {} from {}",
+            description,
+            input.display()
+        ));
+
+        self.record(source, url, input, true)?;
+
+        Ok(input.to_path_buf())
+    }
+
+    async fn fetch_cached(
+        &self,
+        url: &str,
+        description: &str,
+        source: &str,
+        reporter: &dyn Reporter,
+    ) -> anyhow::Result<PathBuf> {
+        let file_path = self.file_path(url);
+        let pinned = self.pinned(source);
+
+        match verify(url, &file_path, reporter)? {
+            Some(metadata) if self.offline || self.is_current(&metadata, pinned.as_deref()) => {
+                reporter.done(&format!(
+                    "Using cached {} from {}",
+                    description,
+                    file_path.display()
+                ));
+            }
+            Some(mut metadata) => {
+                reporter.step(&format!("Checking {} for a newer {}", url, description));
+
+                match download_with_progress(url, &file_path, Some(&metadata), reporter).await {
+                    Ok(Download::NotModified) => {
+                        metadata.fetched_at = SystemTime::now();
+                        metadata.save(&file_path)?;
+
+                        reporter.done(&format!("Cached {} is up to date", description));
+                    }
+                    Ok(Download::Downloaded) => {
+                        reporter.done(&format!("Downloaded a newer {}", description));
+                    }
+                    Err(e) => {
+                        reporter.warn(&format!(
+                            "Failed to revalidate the cached {} ({:#}), using it anyway",
+                            description, e
+                        ));
+                    }
+                }
+            }
+            None if self.offline => {
+                return Err(Error::NotCached {
+                    description: description.to_string(),
+                    name: source.to_string(),
+                    root: self.root.clone(),
+                }
+                .into());
+            }
+            None => {
+                reporter.step(&format!("Downloading {} from {}", description, url));
+
+                download_with_progress(url, &file_path, None, reporter)
+                    .await
+                    .map_err(|e| Error::Download {
+                        url: url.to_string(),
+                        source: e,
+                    })?;
+
+                reporter.done("Download complete");
+            }
+        }
+
+        if let Some(mut metadata) = CacheMetadata::load(&file_path)?
+            && metadata.sources.insert(source.to_string())
+        {
+            metadata.save(&file_path)?;
+        }
+
+        Ok(file_path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::report::Silent;
+
+    use super::*;
+
+    #[test]
+    fn unreadable_metadata_is_a_cache_miss() {
+        let dir = TempDir::new("cache").unwrap();
+        let file_path = dir.path().join(hash_url("https://example.com/file.gz"));
+
+        std::fs::write(&file_path, "hello world").unwrap();
+        std::fs::write(
+            CacheMetadata::path_for(&file_path),
+            "{\"url\": \"https://exa",
+        )
+        .unwrap();
+
+        let metadata = verify("https://example.com/file.gz", &file_path, &Silent).unwrap();
+
+        assert!(metadata.is_none());
+        assert!(!file_path.exists());
+    }
+}
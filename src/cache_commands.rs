@@ -0,0 +1,154 @@
+use std::{fs::File, time::Duration};
+
+use console::{Term, style};
+use indicatif::HumanBytes;
+
+use odict_convert::{
+    cache::{Cache, CacheIndex, CacheMetadata},
+    utils::sha256_reader,
+};
+
+pub fn list(cache: &Cache, term: &Term) -> anyhow::Result<()> {
+    let index = CacheIndex::load(&cache.root)?;
+
+    if index.entries.is_empty() {
+        term.write_line(&format!(
+            "📭 The cache at {} is empty",
+            cache.root.display()
+        ))?;
+        return Ok(());
+    }
+
+    for entry in &index.entries {
+        let metadata = &entry.metadata;
+        let sources = metadata.sources.iter().cloned().collect::<Vec<_>>();
+        let file_name = entry.path.file_name().unwrap_or_default().to_string_lossy();
+
+        term.write_line(&format!(
+            "{} {}",
+            style(&file_name[..12.min(file_name.len())]).bold(),
+            metadata.url
+        ))?;
+        term.write_line(&format!(
+            "    {} · fetched {} · used by {}",
+            HumanBytes(metadata.size),
+            humantime::format_rfc3339_seconds(metadata.fetched_at),
+            if sources.is_empty() {
+                "unknown".to_string()
+            } else {
+                sources.join(", ")
+            }
+        ))?;
+    }
+
+    term.write_line(&format!(
+        "📦 {} files, {} in {}",
+        index.entries.len(),
+        HumanBytes(index.total_size()),
+        cache.root.display()
+    ))?;
+
+    if !index.stray.is_empty() {
+        term.write_line(&format!(
+            "⚠️ {} partial or orphaned files, run `cache prune` to remove them",
+            index.stray.len()
+        ))?;
+    }
+
+    Ok(())
+}
+
+pub fn verify(cache: &Cache, term: &Term) -> anyhow::Result<()> {
+    let index = CacheIndex::load(&cache.root)?;
+    let mut failed = 0;
+
+    for entry in &index.entries {
+        let sha256 = sha256_reader(File::open(&entry.path)?)?;
+
+        if sha256 == entry.metadata.sha256 {
+            term.write_line(&format!("✅ {}", entry.metadata.url))?;
+        } else {
+            failed += 1;
+            term.write_line(&format!("❌ {} (checksum mismatch)", entry.metadata.url))?;
+        }
+    }
+
+    if failed > 0 {
+        anyhow::bail!(
+            "{} of {} cached files failed verification, run `cache prune` to remove them",
+            failed,
+            index.entries.len()
+        );
+    }
+
+    term.write_line(&format!(
+        "✅ All {} cached files verified",
+        index.entries.len()
+    ))?;
+
+    Ok(())
+}
+
+/// Removes partial downloads, orphaned files and files that fail
+/// verification, as well as files not fetched within `older_than`.
+pub fn prune(cache: &Cache, term: &Term, older_than: Option<Duration>) -> anyhow::Result<()> {
+    let index = CacheIndex::load(&cache.root)?;
+    let mut removed = 0;
+    let mut freed = 0;
+
+    for path in &index.stray {
+        freed += path.metadata()?.len();
+        std::fs::remove_file(path)?;
+        removed += 1;
+    }
+
+    for entry in &index.entries {
+        let expired = older_than.is_some_and(|max_age| {
+            entry
+                .metadata
+                .fetched_at
+                .elapsed()
+                .is_ok_and(|age| age > max_age)
+        });
+
+        if expired || sha256_reader(File::open(&entry.path)?)? != entry.metadata.sha256 {
+            term.write_line(&format!("🗑️ Removing {}", entry.metadata.url))?;
+
+            freed += entry.metadata.size;
+            std::fs::remove_file(&entry.path)?;
+            std::fs::remove_file(CacheMetadata::path_for(&entry.path))?;
+            removed += 1;
+        }
+    }
+
+    term.write_line(&format!(
+        "✅ Removed {} files, freeing {}",
+        removed,
+        HumanBytes(freed)
+    ))?;
+
+    Ok(())
+}
+
+/// Removes every file that belongs to the cache, leaving anything else in
+/// its directory alone.
+pub fn clear(cache: &Cache, term: &Term) -> anyhow::Result<()> {
+    let index = CacheIndex::load(&cache.root)?;
+
+    for entry in &index.entries {
+        std::fs::remove_file(&entry.path)?;
+        std::fs::remove_file(CacheMetadata::path_for(&entry.path))?;
+    }
+
+    for path in &index.stray {
+        std::fs::remove_file(path)?;
+    }
+
+    term.write_line(&format!(
+        "✅ Cleared {} from {}",
+        HumanBytes(index.total_size()),
+        cache.root.display()
+    ))?;
+
+    Ok(())
+}
@@ -1,13 +1,39 @@
-use clap::{Subcommand, command};
+use clap::Subcommand;
 
-use crate::args::WiktionaryArgs;
+use crate::args::{
+    BuildArgs, CEDictArgs, CacheArgs, SourcesArgs, WiktionaryArgs, WiktionaryBilingualArgs,
+    WiktionaryDumpArgs,
+};
 
 #[derive(Debug, Subcommand)]
 pub enum Commands {
     #[command(arg_required_else_help = true)]
     Wiktionary(WiktionaryArgs),
+    #[command(
+        name = "wiktionary-dump",
+        about = "Build Wiktionary dictionaries of many languages from kaikki's all-languages dump in one pass"
+    )]
+    WiktionaryDump(WiktionaryDumpArgs),
+    #[command(
+        name = "wiktionary-bilingual",
+        arg_required_else_help = true,
+        about = "Build a dictionary from one language into another by inverting Wiktionary's translation tables"
+    )]
+    WiktionaryBilingual(WiktionaryBilingualArgs),
     #[command(name = "cedict")]
-    CEDict,
+    CEDict(CEDictArgs),
+    #[command(
+        arg_required_else_help = true,
+        about = "Build every dictionary listed in a manifest"
+    )]
+    Build(BuildArgs),
+    #[command(arg_required_else_help = true, about = "Manage the download cache")]
+    Cache(CacheArgs),
+    #[command(
+        arg_required_else_help = true,
+        about = "Discover what the sources offer"
+    )]
+    Sources(SourcesArgs),
     #[command(name = "test-freq")]
     TestFrequency {
         #[arg(help = "Language code (e.g., cmn for Chinese)")]
@@ -0,0 +1,89 @@
+//! The ways building a dictionary can fail.
+
+use std::path::PathBuf;
+
+/// A failed build, by the stage that failed.
+///
+/// The library returns `anyhow::Error`s, which wrap one of these whenever
+/// the failure belongs to one of the stages. [`Error::find`] digs it out,
+/// e.g. to choose an exit code.
+#[derive(Debug, thiserror::Error)]
+pub enum Error {
+    /// A source file or frequency list couldn't be downloaded
+    #[error("Failed to download {url}")]
+    Download {
+        url: String,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// A source isn't cached and can't be downloaded in offline mode
+    #[error(
+        "The {description} for {name} is not cached in {} and can't be downloaded in offline mode",
+        root.display()
+    )]
+    NotCached {
+        description: String,
+        name: String,
+        root: PathBuf,
+    },
+
+    /// A source doesn't match the lockfile in locked mode
+    #[error("{message}")]
+    Locked { message: String },
+
+    /// A record of a source file couldn't be parsed
+    #[error("Failed to parse line {line}")]
+    Parse {
+        line: usize,
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// Too many records of a source file couldn't be parsed
+    #[error(
+        "Rejected {rejected} of {total} records, more than the allowed {:.2}%",
+        max_rate * 100.0
+    )]
+    Rejected {
+        rejected: usize,
+        total: usize,
+        max_rate: f64,
+    },
+
+    /// The extracted entries couldn't be converted into a dictionary
+    #[error("Failed to convert the dictionary")]
+    Convert {
+        #[source]
+        source: anyhow::Error,
+    },
+
+    /// A dictionary or lockfile couldn't be written
+    #[error("Failed to write {}", path.display())]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: anyhow::Error,
+    },
+}
+
+impl Error {
+    /// Returns the first `Error` in the chain of causes of `error`.
+    pub fn find(error: &anyhow::Error) -> Option<&Error> {
+        error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<Error>())
+    }
+
+    /// The exit code the CLI reports this error with. Failures that aren't
+    /// an `Error` exit with 1, and usage errors with 2.
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            Error::Download { .. } | Error::NotCached { .. } => 3,
+            Error::Parse { .. } | Error::Rejected { .. } => 4,
+            Error::Convert { .. } => 5,
+            Error::Write { .. } => 6,
+            Error::Locked { .. } => 7,
+        }
+    }
+}
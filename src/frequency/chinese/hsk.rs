@@ -1,11 +1,9 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, fs::File, io::BufReader};
 
-use console::Term;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    frequency::utils::map_to_ranks_with_sort,
-    utils::{download_with_progress, hash_url, read_file, write_file},
+    cache::Cache, error::Error, frequency::utils::map_to_ranks_with_sort, report::Reporter,
 };
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -14,57 +12,49 @@ struct HskWord {
     q: u32,    // frequency score (lower = more common)
 }
 
-async fn get_hsk_level_data(level: u8, term: &Term) -> anyhow::Result<Vec<HskWord>> {
+async fn get_hsk_level_data(
+    level: u8,
+    reporter: &dyn Reporter,
+    cache: &Cache,
+) -> anyhow::Result<Vec<HskWord>> {
     let url = format!(
         "https://raw.githubusercontent.com/TheOpenDictionary/complete-hsk-vocabulary/refs/heads/main/wordlists/exclusive/new/{}.min.json",
         level
     );
 
-    let file_path = PathBuf::from(".data").join(&hash_url(&url));
-
-    let content = match read_file(&file_path)? {
-        Some(content) => {
-            term.write_line(&format!(
-                "✅ Using cached HSK level {} data from {}",
-                level,
-                file_path.display()
-            ))?;
-            content
-        }
-        None => {
-            term.write_line(&format!(
-                "⬇️ Downloading HSK level {} data from {}...",
-                level, url
-            ))?;
-
-            let content = download_with_progress(&url, &file_path).await?;
-
-            term.clear_line()?;
-            term.write_line(&format!("✅ HSK level {} download complete", level))?;
-
-            write_file(&file_path, &content)?;
-
-            content
-        }
-    };
-
-    let words: Vec<HskWord> = serde_json::from_slice(&content)?;
-
-    term.write_line(&format!(
-        "✅ Loaded {} words from HSK level {}",
+    let file_path = cache
+        .fetch(
+            &url,
+            &format!("HSK level {} data", level),
+            &format!("frequency/hsk/{}", level),
+            reporter,
+        )
+        .await?;
+
+    let words: Vec<HskWord> = serde_json::from_reader(BufReader::new(File::open(&file_path)?))
+        .map_err(|e| Error::Parse {
+            line: e.line(),
+            source: anyhow::anyhow!("{}: {}", file_path.display(), e),
+        })?;
+
+    reporter.done(&format!(
+        "Loaded {} words from HSK level {}",
         words.len(),
         level
-    ))?;
+    ));
 
     Ok(words)
 }
 
-pub async fn get_hsk_ranks(term: &Term) -> anyhow::Result<HashMap<String, u32>> {
+pub async fn get_hsk_ranks(
+    reporter: &dyn Reporter,
+    cache: &Cache,
+) -> anyhow::Result<HashMap<String, u32>> {
     // Load all 7 HSK levels and collect words with their level info
     let mut level_words: Vec<(String, u8, u32)> = Vec::new(); // (word, level, original_freq)
 
     for level in 1..=7 {
-        let words = get_hsk_level_data(level, term).await?;
+        let words = get_hsk_level_data(level, reporter, cache).await?;
 
         for word in words {
             level_words.push((word.s, level, word.q));
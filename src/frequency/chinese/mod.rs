@@ -2,9 +2,12 @@ mod hsk;
 
 use std::collections::HashMap;
 
-use console::Term;
-
-use crate::frequency::{traits::FrequencyMapImpl, utils::map_to_ranks};
+use crate::{
+    cache::Cache,
+    error::Error,
+    frequency::{traits::FrequencyMapImpl, utils::map_to_ranks},
+    report::Reporter,
+};
 
 #[derive(Debug, Clone)]
 pub struct ChineseFrequencyMap {
@@ -13,9 +16,13 @@ pub struct ChineseFrequencyMap {
 
 #[async_trait::async_trait(?Send)]
 impl<'a, 'b> FrequencyMapImpl<'a, 'b> for ChineseFrequencyMap {
-    async fn new(_language: &'a str, term: &'b Term) -> anyhow::Result<Option<Self>> {
-        let simplified = super::ost::get_subtitle_frequencies("zh_CN", term).await?;
-        let traditional = super::ost::get_subtitle_frequencies("zh_TW", term).await?;
+    async fn new(
+        _language: &'a str,
+        reporter: &'b dyn Reporter,
+        cache: &'b Cache,
+    ) -> anyhow::Result<Option<Self>> {
+        let simplified = super::ost::get_subtitle_frequencies("zh_CN", reporter, cache).await?;
+        let traditional = super::ost::get_subtitle_frequencies("zh_TW", reporter, cache).await?;
 
         let mut ranks = traditional;
 
@@ -23,17 +30,18 @@ impl<'a, 'b> FrequencyMapImpl<'a, 'b> for ChineseFrequencyMap {
 
         ranks = map_to_ranks(&ranks);
 
-        match hsk::get_hsk_ranks(&term).await {
+        match hsk::get_hsk_ranks(reporter, cache).await {
             Ok(hsk_map) => {
                 for (word, hsk_rank) in hsk_map.iter() {
                     ranks.insert(word.clone(), *hsk_rank);
                 }
             }
+            Err(e) if matches!(Error::find(&e), Some(Error::Locked { .. })) => return Err(e),
             Err(e) => {
-                term.write_line(&format!(
-                    "⚠️ Failed to load HSK data ({}), falling back to OpenSubtitles only",
+                reporter.warn(&format!(
+                    "Failed to load HSK data ({:#}), falling back to OpenSubtitles only",
                     e
-                ))?;
+                ));
             }
         }
 
@@ -54,12 +62,12 @@ impl<'a, 'b> FrequencyMapImpl<'a, 'b> for ChineseFrequencyMap {
         let exact_match = self.map.get(word).cloned();
         let char_based = self.get_character_frequency(word);
 
-        return match (exact_match, char_based) {
+        match (exact_match, char_based) {
             (Some(exact_rank), Some(char_rank)) => [exact_rank, char_rank].into_iter().min(),
             (Some(exact_rank), None) => Some(exact_rank),
             (None, Some(char_rank)) => Some(char_rank),
             (None, None) => None,
-        };
+        }
     }
 }
 
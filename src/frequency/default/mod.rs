@@ -1,9 +1,13 @@
 use std::collections::HashMap;
 
-use console::Term;
 use isolang::Language;
 
-use crate::frequency::{ost::get_subtitle_frequencies, utils::map_to_ranks};
+use crate::{
+    cache::Cache,
+    error::Error,
+    frequency::{ost::get_subtitle_frequencies, utils::map_to_ranks},
+    report::Reporter,
+};
 
 #[derive(Debug, Clone)]
 pub struct DefaultFrequencyMap {
@@ -12,21 +16,34 @@ pub struct DefaultFrequencyMap {
 
 #[async_trait::async_trait(?Send)]
 impl<'a, 'b> super::traits::FrequencyMapImpl<'a, 'b> for DefaultFrequencyMap {
-    async fn new(language: &'a str, term: &'b Term) -> anyhow::Result<Option<Self>> {
+    async fn new(
+        language: &'a str,
+        reporter: &'b dyn Reporter,
+        cache: &'b Cache,
+    ) -> anyhow::Result<Option<Self>> {
         if let Some(lang) = Language::from_639_3(language).and_then(|l| l.to_639_1()) {
-            let frequencies = get_subtitle_frequencies(lang, term)
-                .await
-                .unwrap_or_default();
+            let frequencies = match get_subtitle_frequencies(lang, reporter, cache).await {
+                Ok(frequencies) => frequencies,
+                // A build that must match the lockfile can't go on unranked
+                Err(e) if matches!(Error::find(&e), Some(Error::Locked { .. })) => return Err(e),
+                Err(e) => {
+                    reporter.warn(&format!(
+                        "Failed to load subtitle frequencies ({:#}), entries won't be ranked",
+                        e
+                    ));
+                    HashMap::new()
+                }
+            };
 
             return Ok(Some(Self {
                 map: map_to_ranks(&frequencies),
             }));
         }
 
-        term.write_line(&format!(
-            "❌ Couldn't find frequency map for language \"{}\"",
+        reporter.warn(&format!(
+            "Couldn't find frequency map for language \"{}\"",
             language
-        ))?;
+        ));
 
         Ok(None)
     }
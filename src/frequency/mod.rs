@@ -4,24 +4,78 @@ mod ost;
 mod traits;
 mod utils;
 
-use console::Term;
+use isolang::Language;
+use serde::Deserialize;
 
-use crate::frequency::traits::FrequencyMapImpl;
+use crate::{cache::Cache, frequency::traits::FrequencyMapImpl, report::Reporter};
 
+/// Where the frequencies entries are ranked by come from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FrequencySource {
+    /// HSK levels and subtitles for Mandarin, subtitles for everything else
+    #[default]
+    Auto,
+    /// Don't rank entries
+    None,
+    /// OpenSubtitles word frequencies
+    Opensubtitles,
+    /// HSK levels, falling back to Chinese OpenSubtitles frequencies
+    Hsk,
+}
+
+/// Whether there are OpenSubtitles word frequencies for `language`, an
+/// ISO 639-3 code.
+pub fn has_subtitle_frequencies(language: &str) -> bool {
+    match language {
+        "cmn" => ost::is_available("zh_CN"),
+        _ => Language::from_639_3(language)
+            .and_then(|l| l.to_639_1())
+            .is_some_and(ost::is_available),
+    }
+}
+
+/// Ranks words of a language by how common they are, 1 being the most common.
 #[derive(Debug)]
 pub struct FrequencyMap<'a, 'b> {
     map: Option<Box<dyn FrequencyMapImpl<'a, 'b>>>, // word -> rank
 }
 
 impl FrequencyMap<'_, '_> {
-    pub async fn new(language: &str, term: &Term) -> anyhow::Result<Option<Self>> {
-        let map: Option<Box<dyn FrequencyMapImpl<'_, '_>>> = match language {
-            "cmn" => chinese::ChineseFrequencyMap::new(language, term)
-                .await?
-                .map(|f| Box::new(f) as Box<dyn FrequencyMapImpl<'_, '_>>),
-            _ => default::DefaultFrequencyMap::new(language, term)
+    /// Loads the usual frequency data for `language`, an ISO 639-3 code.
+    ///
+    /// Returns `None` if there is no frequency data for the language.
+    pub async fn new(
+        language: &str,
+        reporter: &dyn Reporter,
+        cache: &Cache,
+    ) -> anyhow::Result<Option<Self>> {
+        Self::from_source(FrequencySource::Auto, language, reporter, cache).await
+    }
+
+    /// Loads the frequency data for `language` from `source`.
+    pub async fn from_source(
+        source: FrequencySource,
+        language: &str,
+        reporter: &dyn Reporter,
+        cache: &Cache,
+    ) -> anyhow::Result<Option<Self>> {
+        let source = match (source, language) {
+            (FrequencySource::Auto, "cmn") => FrequencySource::Hsk,
+            (FrequencySource::Auto, _) => FrequencySource::Opensubtitles,
+            (source, _) => source,
+        };
+
+        let map: Option<Box<dyn FrequencyMapImpl<'_, '_>>> = match source {
+            FrequencySource::Hsk => chinese::ChineseFrequencyMap::new(language, reporter, cache)
                 .await?
                 .map(|f| Box::new(f) as Box<dyn FrequencyMapImpl<'_, '_>>),
+            FrequencySource::Opensubtitles => {
+                default::DefaultFrequencyMap::new(language, reporter, cache)
+                    .await?
+                    .map(|f| Box::new(f) as Box<dyn FrequencyMapImpl<'_, '_>>)
+            }
+            FrequencySource::Auto | FrequencySource::None => None,
         };
 
         if map.is_none() {
@@ -31,7 +85,8 @@ impl FrequencyMap<'_, '_> {
         Ok(Some(Self { map }))
     }
 
+    /// Returns the rank of `word`, if it is ranked at all.
     pub fn get_frequency(&self, word: &str) -> Option<u32> {
-        return self.map.as_ref().and_then(|m| m.get_frequency(word));
+        self.map.as_ref().and_then(|m| m.get_frequency(word))
     }
 }
@@ -1,21 +1,39 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::collections::HashMap;
 
-use console::Term;
 use regex::Regex;
 
-use crate::utils::{decompress_gzip, download_with_progress, hash_url, read_file, write_file};
+use crate::{
+    cache::Cache,
+    report::Reporter,
+    utils::{open_input, raw_lines},
+};
+
+/// The languages OpenSubtitles has word frequencies for, by the code used
+/// in their file names (ISO 639-1 mostly).
+const LANGUAGES: &[&str] = &[
+    "af", "ar", "bg", "bn", "br", "bs", "ca", "cs", "da", "de", "el", "en", "eo", "es", "et", "eu",
+    "fa", "fi", "fr", "gl", "he", "hi", "hr", "hu", "hy", "id", "is", "it", "ja", "ka", "kk", "ko",
+    "lt", "lv", "mk", "ml", "ms", "nl", "no", "pl", "pt", "pt_br", "ro", "ru", "si", "sk", "sl",
+    "sq", "sr", "sv", "ta", "te", "th", "tl", "tr", "uk", "ur", "vi", "zh_cn", "zh_tw",
+];
+
+/// Whether OpenSubtitles has word frequencies for `language_code`.
+pub fn is_available(language_code: &str) -> bool {
+    LANGUAGES.contains(&language_code.to_ascii_lowercase().as_str())
+}
 
 fn get_source(_language_code: &str) -> &str {
-    return "OpenSubtitles";
+    "OpenSubtitles"
 }
 
 fn get_version(_language_code: &str) -> &str {
-    return "v2024";
+    "v2024"
 }
 
 pub async fn get_subtitle_frequencies(
     language_code: &str,
-    term: &Term,
+    reporter: &dyn Reporter,
+    cache: &Cache,
 ) -> anyhow::Result<HashMap<String, u32>> {
     let url = format!(
         "https://object.pouta.csc.fi/OPUS-{}/{}/freq/{}.freq.gz",
@@ -24,63 +42,42 @@ pub async fn get_subtitle_frequencies(
         language_code
     );
 
-    let data_dir = PathBuf::from(".data");
-
-    if !data_dir.exists() {
-        std::fs::create_dir_all(&data_dir)?;
-    }
-
-    let file_path = data_dir.join(&hash_url(&url));
-
-    let content = match read_file(&file_path)? {
-        Some(content) => {
-            term.write_line(&format!(
-                "✅ Using cached frequency list from {}",
-                file_path.display()
-            ))?;
-            content
-        }
-        None => {
-            term.write_line(format!("⬇️ Downloading frequency list from {}...", url).as_str())?;
-
-            let content = download_with_progress(&url, &file_path).await?;
-
-            term.clear_line()?;
-            term.write_line("✅ Download complete")?;
-
-            write_file(&file_path, &content)?;
-
-            content
-        }
-    };
+    let file_path = cache
+        .fetch(
+            &url,
+            "frequency list",
+            &format!("frequency/opensubtitles/{}", language_code),
+            reporter,
+        )
+        .await?;
 
     let mut map = HashMap::new();
 
-    let decoded = String::from_utf8(decompress_gzip(&content)?)?;
     let punctuation_regex = Regex::new(r"[^\p{L}]")?;
     let number_regex = Regex::new(r"^\d+$")?;
 
-    for line in decoded.lines() {
+    for line in raw_lines(open_input(&file_path)?) {
+        let line = String::from_utf8_lossy(&line?).into_owned();
         let parts: Vec<&str> = line.split_whitespace().collect();
 
-        if parts.len() >= 2 {
-            if let Ok(frequency) = parts[0].parse::<u32>() {
-                // Strip all punctuation from the word
-                let clean_word = punctuation_regex.replace_all(parts[1], "").to_string();
-                let is_number = number_regex.is_match(&clean_word);
+        if parts.len() >= 2
+            && let Ok(frequency) = parts[0].parse::<u32>()
+        {
+            // Strip all punctuation from the word
+            let clean_word = punctuation_regex.replace_all(parts[1], "").to_string();
+            let is_number = number_regex.is_match(&clean_word);
 
-                // Skip punctuation and numbers
-                if !clean_word.is_empty() && !is_number {
-                    *map.entry(clean_word).or_insert(0) += frequency;
-                }
+            // Skip punctuation and numbers
+            if !clean_word.is_empty() && !is_number {
+                *map.entry(clean_word).or_insert(0) += frequency;
             }
         }
     }
 
-    term.write_line(&format!(
-        "✅ Loaded subtitle frequency data for {} words",
+    reporter.done(&format!(
+        "Loaded subtitle frequency data for {} words",
         map.len()
-    ))?;
+    ));
 
     Ok(map)
 }
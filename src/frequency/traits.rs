@@ -1,9 +1,13 @@
+use crate::{cache::Cache, report::Reporter};
 use async_trait::async_trait;
-use console::Term;
 
 #[async_trait(?Send)]
-pub trait FrequencyMapImpl<'a, 'b>: std::fmt::Debug {
-    async fn new(language: &'a str, term: &'b Term) -> anyhow::Result<Option<Self>>
+pub trait FrequencyMapImpl<'a, 'b>: std::fmt::Debug + Send + Sync {
+    async fn new(
+        language: &'a str,
+        reporter: &'b dyn Reporter,
+        cache: &'b Cache,
+    ) -> anyhow::Result<Option<Self>>
     where
         Self: Sized;
 
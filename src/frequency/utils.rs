@@ -10,7 +10,8 @@ pub fn map_to_ranks_with_sort<T: Eq + Ord>(
 ) -> HashMap<String, u32> {
     let mut sorted: Vec<_> = map.iter().collect();
 
-    sorted.sort_by(|a, b| sort_by(a.1, b.1));
+    // Words that compare equal are ranked alphabetically so ranks are stable
+    sorted.sort_by(|a, b| sort_by(a.1, b.1).then_with(|| a.0.cmp(b.0)));
 
     let mut ranks = HashMap::new();
 
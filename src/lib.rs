@@ -0,0 +1,42 @@
+//! Converts dictionaries from other formats to [ODict](https://odict.org).
+//!
+//! Each supported source has a [`Processor`](processors::Processor) that
+//! downloads it (or reads a local copy), extracts its entries and converts
+//! them into an [`odict::Dictionary`], ranking entries with a
+//! [`FrequencyMap`](frequency::FrequencyMap) where one is available:
+//!
+//! ```no_run
+//! use std::path::Path;
+//!
+//! use odict_convert::{
+//!     cache::Cache,
+//!     processors::{Processor, WiktionaryProcessor},
+//!     report::Silent,
+//!     utils::{Compression, save_dictionary},
+//! };
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let cache = Cache::default();
+//! let dictionary = WiktionaryProcessor::new()?
+//!     .process(&Silent, &cache, Some("eng".to_string()), None)
+//!     .await?;
+//!
+//! save_dictionary(&Silent, &dictionary, Path::new("eng.odict"), &Compression::default())?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Downloads are cached in a [`Cache`](cache::Cache), and progress is
+//! reported through a [`Reporter`](report::Reporter). The library never
+//! writes to the terminal itself. Failures are `anyhow::Error`s that carry
+//! an [`Error`](error::Error) naming the stage that failed.
+
+pub mod cache;
+pub mod error;
+pub mod frequency;
+pub mod manifest;
+pub mod processors;
+pub mod report;
+pub mod utils;
+
+mod archive;
@@ -1,68 +1,345 @@
-use std::path::PathBuf;
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Mutex, PoisonError},
+    time::Duration,
+};
 
-use self::commands::Commands;
-use clap::{Parser, command};
+use self::{
+    args::{CacheCommands, LogFormat, SourcesCommands, parse_source_input},
+    commands::Commands,
+};
+use clap::{CommandFactory, Parser, error::ErrorKind};
 use console::Term;
-use processors::{CEDictProcessor, Processor, WiktionaryProcessor};
-use utils::save_dictionary;
+use odict::Dictionary;
+use odict_convert::{
+    cache::{self, Cache, SourceLock},
+    error::Error,
+    manifest,
+    processors::{
+        CEDictProcessor, Processor, WiktionaryBilingualProcessor, WiktionaryProcessor,
+        wiktionary::{Edition, Languages},
+    },
+    report::{JsonReporter, PlainReporter, Quiet, Reporter, TermReporter},
+    utils::{Compression, default_output_path, save_dictionary},
+};
 
 mod args;
+mod cache_commands;
 mod commands;
-mod frequency;
-mod processors;
-mod progress;
+mod sources_commands;
 mod test_frequency;
-mod utils;
 
 #[derive(Debug, Parser)]
 #[command(name = "odict-convert")]
 #[command(about = "Convert other dictionary formats to .odict files", long_about = None)]
+#[command(
+    after_help = "Exit codes: 1 other failures, 2 usage errors, 3 download failures, \
+                      4 parse failures, 5 conversion failures, 6 write failures, \
+                      7 lockfile mismatches"
+)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
 
     #[arg(short, long, help = "Path to save the output dictionary file")]
     output: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Directory to cache downloads in [default: $XDG_CACHE_HOME/odict-convert]"
+    )]
+    cache_dir: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Revalidate cached downloads with their servers even if they are still fresh"
+    )]
+    refresh: bool,
+
+    #[arg(
+        long,
+        global = true,
+        default_value = "7days",
+        value_parser = humantime::parse_duration,
+        help = "How long a cached download is used before it is revalidated (e.g. 12h, 30days)"
+    )]
+    max_age: Duration,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Only use cached downloads and local inputs, never the network"
+    )]
+    offline: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "SOURCE=PATH",
+        value_parser = parse_source_input,
+        help = "Local file to use for a frequency source instead of downloading it \
+                (e.g. frequency/opensubtitles/en=en.freq.gz or frequency/hsk/1=1.min.json)"
+    )]
+    frequency_input: Vec<(String, PathBuf)>,
+
+    #[arg(
+        long,
+        global = true,
+        default_value = "sources.lock",
+        help = "Lockfile recording the exact sources used by builds"
+    )]
+    lockfile: PathBuf,
+
+    #[arg(
+        long,
+        global = true,
+        help = "Refuse to build if a source doesn't match the lockfile"
+    )]
+    locked: bool,
+
+    #[arg(short, long, global = true, help = "Only report warnings")]
+    quiet: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_enum,
+        default_value_t = LogFormat::Auto,
+        help = "How progress is reported"
+    )]
+    log_format: LogFormat,
 }
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     let args = Cli::parse();
+
+    match run(args).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("❌ {:#}", e);
+
+            ExitCode::from(Error::find(&e).map_or(1, Error::exit_code))
+        }
+    }
+}
+
+async fn run(args: Cli) -> anyhow::Result<()> {
     let term = Term::stdout();
+    let reporter: Box<dyn Reporter> = match args.log_format {
+        LogFormat::Auto if term.is_term() => Box::new(TermReporter::new(term.clone())),
+        LogFormat::Auto | LogFormat::Plain => Box::new(PlainReporter::default()),
+        LogFormat::Tty => Box::new(TermReporter::new(term.clone())),
+        LogFormat::Json => Box::new(JsonReporter::default()),
+    };
+    let reporter = match args.quiet {
+        true => Box::new(Quiet(reporter)),
+        false => reporter,
+    };
+    let cache = Cache {
+        root: args.cache_dir.clone().unwrap_or_else(cache::default_root),
+        refresh: args.refresh,
+        max_age: Some(args.max_age),
+        offline: args.offline,
+        inputs: args.frequency_input.iter().cloned().collect(),
+        lock: Mutex::new(SourceLock::load(&args.lockfile)?),
+        locked: args.locked,
+        fetched: Mutex::default(),
+    };
 
     match &args.command {
         Commands::TestFrequency { language, word } => {
-            test_frequency::test_frequency(language, word, &term).await
+            test_frequency::test_frequency(language, word, &term, &reporter, &cache).await?
         }
-        _ => {
-            let dictionary = match &args.command {
-                Commands::Wiktionary(wiktionary_args) => WiktionaryProcessor::new()
-                    .unwrap()
-                    .process(&term, Some(wiktionary_args.language.clone()))
-                    .await
-                    .unwrap(),
-                Commands::CEDict => CEDictProcessor::new()
-                    .unwrap()
-                    .process(&term, Some("cmn".to_string()))
-                    .await
-                    .unwrap(),
-                Commands::TestFrequency { .. } => unreachable!(),
-            };
-
-            let (command_name, language) = match &args.command {
-                Commands::Wiktionary(wiktionary_args) => {
-                    ("wiktionary", wiktionary_args.language.clone())
-                }
-                Commands::CEDict => ("cedict", "zho-eng".to_string()),
-                Commands::TestFrequency { .. } => unreachable!(),
-            };
-
-            let output_path: PathBuf = match &args.output {
-                Some(path) => path.clone().into(),
-                None => format!("out/{}/{}.odict", command_name, language).into(),
-            };
-
-            save_dictionary(term, &dictionary, &output_path).unwrap();
+        Commands::Cache(cache_args) => match &cache_args.command {
+            CacheCommands::List => cache_commands::list(&cache, &term),
+            CacheCommands::Verify => cache_commands::verify(&cache, &term),
+            CacheCommands::Prune { older_than } => {
+                cache_commands::prune(&cache, &term, *older_than)
+            }
+            CacheCommands::Clear => cache_commands::clear(&cache, &term),
+        }?,
+        Commands::Sources(sources_args) => match &sources_args.command {
+            SourcesCommands::Wiktionary { list: _, input } => {
+                sources_commands::list_wiktionary(&reporter, &cache, input.as_deref(), &term)
+                    .await?
+            }
+        },
+        Commands::Build(build_args) => {
+            let manifest = manifest::Manifest::load(&build_args.manifest)?;
+
+            manifest::build(&manifest, build_args.jobs, &reporter, &cache).await?;
+
+            if !cache.locked {
+                cache
+                    .lock
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .save(&args.lockfile)?;
+            }
+        }
+        Commands::Wiktionary(wiktionary_args) => {
+            let languages = wiktionary_args.options.languages()?;
+            let language = language(&languages, &wiktionary_args.language);
+            let output_path = args.output_path(
+                "wiktionary",
+                &wiktionary_args.options.edition.output_name(&language),
+            );
+            let options = wiktionary_args.options.options().for_output(&output_path);
+            let dictionary = WiktionaryProcessor::with_options(options)?
+                .process(
+                    &reporter,
+                    &cache,
+                    Some(language),
+                    wiktionary_args.input.clone(),
+                )
+                .await?;
+
+            write_dictionary(&args, &reporter, &cache, &dictionary, &output_path)?;
+        }
+        Commands::WiktionaryDump(dump_args) => {
+            if args.output.is_some() {
+                anyhow::bail!(
+                    "wiktionary-dump writes one dictionary per language, use --output-dir instead of --output"
+                );
+            }
+
+            let known = dump_args.options.languages()?;
+            let languages: Vec<String> = dump_args
+                .languages
+                .iter()
+                .map(|value| language(&known, value))
+                .collect();
+
+            // Rejected lines of every language go to a single file
+            let edition = dump_args.options.edition;
+            let options = dump_args
+                .options
+                .options()
+                .for_output(&dump_args.output_dir.join("dump"));
+
+            WiktionaryProcessor::with_options(options)?
+                .process_dump(
+                    &reporter,
+                    &cache,
+                    &languages,
+                    dump_args.input.clone(),
+                    |language, dictionary| {
+                        save_dictionary(
+                            &reporter,
+                            dictionary,
+                            &dump_args
+                                .output_dir
+                                .join(format!("{}.odict", edition.output_name(language))),
+                            &Compression::default(),
+                        )
+                    },
+                )
+                .await?;
+
+            if !cache.locked {
+                cache
+                    .lock
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .save(&args.lockfile)?;
+            }
+        }
+        Commands::WiktionaryBilingual(bilingual_args) => {
+            let languages = bilingual_args.options.languages()?;
+            let source = language(&languages, &bilingual_args.source);
+            let target = language(&languages, &bilingual_args.target);
+
+            // The edition follows the target language, so --edition can only repeat it
+            let edition = bilingual_args.options.edition;
+
+            if edition != Edition::default() && edition.gloss_language() != target {
+                anyhow::bail!(
+                    "The edition of a bilingual dictionary follows its target language, leave out --edition"
+                );
+            }
+
+            let output_path =
+                args.output_path("wiktionary-bilingual", &format!("{}-{}", source, target));
+            let options = bilingual_args.options.options().for_output(&output_path);
+            let dictionary = WiktionaryBilingualProcessor::with_options(options, &target)?
+                .process(
+                    &reporter,
+                    &cache,
+                    Some(source),
+                    bilingual_args.input.clone(),
+                )
+                .await?;
+
+            write_dictionary(&args, &reporter, &cache, &dictionary, &output_path)?;
+        }
+        Commands::CEDict(cedict_args) => {
+            let output_path = args.output_path("cedict", "zho-eng");
+            let dictionary = CEDictProcessor::new()?
+                .process(
+                    &reporter,
+                    &cache,
+                    Some("cmn".to_string()),
+                    cedict_args.input.clone(),
+                )
+                .await?;
+
+            write_dictionary(&args, &reporter, &cache, &dictionary, &output_path)?;
         }
     }
+
+    Ok(())
+}
+
+impl Cli {
+    /// Where a single-source command writes its dictionary.
+    fn output_path(&self, source: &str, name: &str) -> PathBuf {
+        match &self.output {
+            Some(path) => path.into(),
+            None => default_output_path(source, name),
+        }
+    }
+}
+
+/// Resolves a language given on the command line into the ISO 639-3 code
+/// dictionaries are built and named by. Which languages there are is only
+/// known once `--profiles` is read, so unknown ones are reported the way
+/// clap reports invalid arguments.
+fn language(languages: &Languages, value: &str) -> String {
+    languages.code(value).unwrap_or_else(|| {
+        Cli::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!(
+                    "unknown language \"{}\", expected an ISO 639-1 or 639-3 code",
+                    value
+                ),
+            )
+            .exit()
+    })
+}
+
+/// Saves a dictionary built by a single-source command and records its
+/// sources in the lockfile.
+fn write_dictionary(
+    args: &Cli,
+    reporter: &dyn Reporter,
+    cache: &Cache,
+    dictionary: &Dictionary,
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    save_dictionary(reporter, dictionary, output_path, &Compression::default())?;
+
+    if !cache.locked {
+        cache
+            .lock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .save(&args.lockfile)?;
+    }
+
+    Ok(())
 }
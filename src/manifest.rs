@@ -0,0 +1,373 @@
+//! Builds many dictionaries in one run from a manifest such as `dicts.toml`.
+
+use std::{
+    collections::{HashMap, HashSet, hash_map::Entry},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+    thread,
+};
+
+use odict::{Dictionary, PartOfSpeech};
+use regex::Regex;
+use serde::Deserialize;
+use tokio::{
+    runtime::{Handle, RuntimeFlavor},
+    task::block_in_place,
+};
+
+use crate::{
+    cache::Cache,
+    frequency::{FrequencyMap, FrequencySource},
+    processors::{
+        CEDictProcessor, Processor, WiktionaryProcessor,
+        wiktionary::{Languages, WiktionaryOptions, prune_cross_references},
+    },
+    report::Reporter,
+    utils::{Compression, default_output_path, save_dictionary},
+};
+
+/// A list of dictionaries to build in one run, usually read from `dicts.toml`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    /// How many dictionaries are built at the same time (one if unset).
+    /// Each Wiktionary dictionary being built holds its whole extract in
+    /// memory, several gigabytes for the larger languages.
+    pub concurrency: Option<NonZeroUsize>,
+
+    #[serde(default, rename = "dictionary")]
+    pub dictionaries: Vec<Target>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    Wiktionary,
+    Cedict,
+}
+
+/// A dictionary to build.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Target {
+    pub source: Source,
+
+    /// The language to build the dictionary for (required for Wiktionary)
+    pub language: Option<String>,
+
+    /// Where to write the dictionary, `out/<source>/<language>.odict` by
+    /// default, or `out/wiktionary/<language>-<gloss language>.odict` for
+    /// Wiktionary editions other than English
+    pub output: Option<PathBuf>,
+
+    /// A local source file to use instead of downloading one
+    pub input: Option<PathBuf>,
+
+    #[serde(default)]
+    pub compression: Compression,
+
+    #[serde(default)]
+    pub frequency: FrequencySource,
+
+    #[serde(default)]
+    pub filters: Filters,
+
+    /// Options for Wiktionary dictionaries
+    #[serde(default)]
+    pub wiktionary: WiktionaryOptions,
+}
+
+/// Which entries and senses of a dictionary are kept.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Filters {
+    /// Only keep entries ranked at or above this rank
+    pub max_rank: Option<u32>,
+
+    /// Only keep senses with these parts of speech
+    #[serde(default)]
+    pub pos: Vec<PartOfSpeech>,
+
+    /// Only keep entries whose term matches this regular expression
+    pub term_pattern: Option<String>,
+}
+
+impl Manifest {
+    /// Reads a manifest, resolving relative paths against its directory.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut manifest: Self = toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid manifest {}: {}", path.display(), e))?;
+
+        let base = path.parent().unwrap_or(Path::new(""));
+        let mut outputs = HashSet::new();
+
+        for target in &mut manifest.dictionaries {
+            target.wiktionary = target.wiktionary.clone().relative_to(base);
+
+            if target.source == Source::Wiktionary {
+                let Some(language) = &target.language else {
+                    anyhow::bail!(
+                        "Wiktionary dictionaries in {} need a language",
+                        path.display()
+                    );
+                };
+
+                // Languages can be given by ISO 639-1 or 639-3 code, but
+                // are built and named by their ISO 639-3 code
+                let languages = Languages::load(target.wiktionary.profiles.as_deref())?;
+
+                target.language = Some(languages.profile(language)?.code);
+            }
+
+            let output = base.join(target.output_path());
+
+            if !outputs.insert(output.clone()) {
+                anyhow::bail!(
+                    "{} is the output of more than one dictionary in {}",
+                    output.display(),
+                    path.display()
+                );
+            }
+
+            target.input = target.input.as_ref().map(|input| base.join(input));
+            target.wiktionary = target.wiktionary.clone().for_output(&output);
+            target.output = Some(output);
+        }
+
+        Ok(manifest)
+    }
+}
+
+impl Target {
+    fn language(&self) -> Option<String> {
+        match self.source {
+            Source::Wiktionary => self.language.clone(),
+            Source::Cedict => Some("cmn".to_string()),
+        }
+    }
+
+    /// Where the frequencies the dictionary is ranked by come from.
+    fn frequency_source(&self, language: &str) -> anyhow::Result<FrequencySource> {
+        Ok(match (self.frequency, self.source) {
+            (FrequencySource::Auto, Source::Wiktionary) => {
+                WiktionaryProcessor::with_options(self.wiktionary.clone())?
+                    .frequency_source(language)
+            }
+            (FrequencySource::Auto, Source::Cedict) => {
+                CEDictProcessor::new()?.frequency_source(language)
+            }
+            (source, _) => source,
+        })
+    }
+
+    fn output_path(&self) -> PathBuf {
+        match (&self.output, self.source) {
+            (Some(output), _) => output.clone(),
+            (None, Source::Wiktionary) => default_output_path(
+                "wiktionary",
+                &self
+                    .wiktionary
+                    .edition
+                    .output_name(self.language.as_deref().unwrap_or_default()),
+            ),
+            (None, Source::Cedict) => default_output_path("cedict", "zho-eng"),
+        }
+    }
+
+    async fn fetch(&self, reporter: &dyn Reporter, cache: &Cache) -> anyhow::Result<PathBuf> {
+        let language = self.language();
+        let input = self.input.clone();
+
+        match self.source {
+            Source::Wiktionary => {
+                WiktionaryProcessor::with_options(self.wiktionary.clone())?
+                    .fetch(reporter, cache, &language, input)
+                    .await
+            }
+            Source::Cedict => {
+                CEDictProcessor::new()?
+                    .fetch(reporter, cache, &language, input)
+                    .await
+            }
+        }
+    }
+
+    fn build(
+        &self,
+        reporter: &dyn Reporter,
+        frequency_map: &Option<FrequencyMap>,
+        path: &Path,
+    ) -> anyhow::Result<Dictionary> {
+        let language = self.language();
+
+        let dictionary = match self.source {
+            Source::Wiktionary => WiktionaryProcessor::with_options(self.wiktionary.clone())?
+                .build(reporter, frequency_map, path, language)?,
+            Source::Cedict => {
+                CEDictProcessor::new()?.build(reporter, frequency_map, path, language)?
+            }
+        };
+
+        let dictionary = self.filters.apply(dictionary)?;
+
+        // Cross-references to entries the filters left out can't be followed
+        Ok(match self.source {
+            Source::Wiktionary => prune_cross_references(dictionary),
+            Source::Cedict => dictionary,
+        })
+    }
+}
+
+impl Filters {
+    fn apply(&self, mut dictionary: Dictionary) -> anyhow::Result<Dictionary> {
+        let pattern = self.term_pattern.as_deref().map(Regex::new).transpose()?;
+
+        dictionary.entries = dictionary
+            .entries
+            .into_iter()
+            .filter(|entry| match self.max_rank {
+                Some(max_rank) => entry.rank.is_some_and(|rank| rank <= max_rank),
+                None => true,
+            })
+            .filter(|entry| pattern.as_ref().is_none_or(|p| p.is_match(&entry.term)))
+            .filter_map(|mut entry| {
+                if !self.pos.is_empty() {
+                    for ety in &mut entry.etymologies {
+                        ety.senses.retain(|sense| self.pos.contains(&sense.pos));
+                    }
+
+                    entry.etymologies.retain(|ety| !ety.senses.is_empty());
+                }
+
+                (!entry.etymologies.is_empty()).then_some(entry)
+            })
+            .collect();
+
+        Ok(dictionary)
+    }
+}
+
+/// Builds every dictionary in `manifest`, at most `concurrency` at a time
+/// (the manifest's concurrency, or one if neither is given).
+///
+/// Sources and frequency maps are fetched up front, once each, no matter how
+/// many dictionaries use them. A dictionary that fails to build doesn't stop
+/// the others, but makes the whole build fail at the end.
+pub async fn build(
+    manifest: &Manifest,
+    concurrency: Option<NonZeroUsize>,
+    reporter: &dyn Reporter,
+    cache: &Cache,
+) -> anyhow::Result<()> {
+    let mut frequency_maps: HashMap<(FrequencySource, String), Option<FrequencyMap>> =
+        HashMap::new();
+    // The source file of each dictionary and the key of its frequency map,
+    // in the order of the manifest
+    let mut fetched = vec![];
+
+    for target in &manifest.dictionaries {
+        let path = target.fetch(reporter, cache).await?;
+        let key = match target.language() {
+            Some(language) => Some((target.frequency_source(&language)?, language)),
+            None => None,
+        };
+
+        if let Some(key) = &key
+            && let Entry::Vacant(slot) = frequency_maps.entry(key.clone())
+        {
+            let (source, language) = slot.key();
+            let map = FrequencyMap::from_source(*source, language, reporter, cache).await?;
+
+            slot.insert(map);
+        }
+
+        fetched.push((path, key));
+    }
+
+    let concurrency = concurrency
+        .or(manifest.concurrency)
+        .map_or(1, NonZeroUsize::get);
+
+    reporter.step(&format!(
+        "Building {} dictionaries, {} at a time",
+        manifest.dictionaries.len(),
+        concurrency
+    ));
+
+    let unranked = None;
+    let queue = Mutex::new(manifest.dictionaries.iter().zip(&fetched));
+    let failures = Mutex::new(vec![]);
+
+    let build_all = || {
+        thread::scope(|scope| {
+            for _ in 0..concurrency {
+                scope.spawn(|| {
+                    loop {
+                        let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+
+                        let Some((target, (path, key))) = next else {
+                            break;
+                        };
+
+                        let output = target.output_path();
+                        let frequency_map = key
+                            .as_ref()
+                            .and_then(|key| frequency_maps.get(key))
+                            .unwrap_or(&unranked);
+
+                        let result =
+                            target
+                                .build(reporter, frequency_map, path)
+                                .and_then(|dictionary| {
+                                    save_dictionary(
+                                        reporter,
+                                        &dictionary,
+                                        &output,
+                                        &target.compression,
+                                    )
+                                });
+
+                        if let Err(e) = result {
+                            reporter.warn(&format!(
+                                "Failed to build {}: {:#}",
+                                output.display(),
+                                e
+                            ));
+
+                            failures
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .push(e);
+                        }
+                    }
+                });
+            }
+        })
+    };
+
+    // The builds block until they are done, so a multi-threaded runtime is
+    // told to move its other tasks off this thread in the meantime
+    match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+        Ok(RuntimeFlavor::MultiThread) => block_in_place(build_all),
+        _ => build_all(),
+    }
+
+    let mut failures = failures
+        .into_inner()
+        .unwrap_or_else(PoisonError::into_inner);
+
+    // The first failure is returned so its cause decides the exit code
+    if !failures.is_empty() {
+        let count = failures.len();
+
+        return Err(failures.swap_remove(0).context(format!(
+            "{} of {} dictionaries failed to build",
+            count,
+            manifest.dictionaries.len()
+        )));
+    }
+
+    Ok(())
+}
@@ -1,13 +1,17 @@
 use std::collections::HashMap;
 
-use console::Term;
 use map_macro::{hash_map, hash_set};
 use odict::{
-    Definition, DefinitionType, Dictionary, Entry, Etymology, Form, FormKind, ID, PartOfSpeech,
+    Definition, DefinitionType, Dictionary, Entry, Etymology, Form, FormKind, PartOfSpeech,
     Pronunciation, Sense,
 };
 
-use crate::{frequency::FrequencyMap, processors::traits::Converter, progress::STYLE_PROGRESS};
+use crate::{
+    frequency::FrequencyMap,
+    processors::traits::Converter,
+    report::{ProgressKind, Reporter},
+    utils::dictionary_id,
+};
 
 use super::schema::CEDictEntry;
 
@@ -18,21 +22,20 @@ impl Converter for CEDictConverter {
 
     fn convert(
         &mut self,
-        term: &Term,
+        reporter: &dyn Reporter,
         frequency_map: &Option<FrequencyMap>,
-        data: &Vec<CEDictEntry>,
-        _language: Option<String>,
+        data: &[CEDictEntry],
+        language: Option<String>,
     ) -> anyhow::Result<Dictionary> {
-        term.write_line("🔄 Converting the dictionary...")?;
+        reporter.step("Converting the dictionary");
 
-        let progress = indicatif::ProgressBar::new(data.len() as u64);
-        progress.set_style(STYLE_PROGRESS.clone());
+        let progress = reporter.progress(ProgressKind::Items(data.len() as u64));
 
         let mut entries: HashMap<String, Entry> = hash_map! {};
 
         for cedict_entry in data {
             progress.inc(1);
-            progress.set_message(cedict_entry.simplified.clone());
+            progress.set_message(&cedict_entry.simplified);
 
             let simplified = cedict_entry.simplified.clone();
             let traditional = cedict_entry.traditional.clone();
@@ -99,13 +102,12 @@ impl Converter for CEDictConverter {
             entries.insert(simplified, entry);
         }
 
-        progress.finish_and_clear();
+        drop(progress);
 
-        term.clear_last_lines(1)?;
-        term.write_line("✅ Conversion complete")?;
+        reporter.done("Conversion complete");
 
         Ok(Dictionary {
-            id: ID::new(),
+            id: dictionary_id("cedict", language.as_deref())?,
             name: Some("CC-CEDICT".to_string()),
             entries: entries.values().cloned().collect(),
         })
@@ -1,5 +1,6 @@
 use crate::processors::traits::Downloader;
 
+#[derive(Default)]
 pub struct CEDictDownloader {}
 
 impl CEDictDownloader {
@@ -13,6 +14,10 @@ impl Downloader for CEDictDownloader {
         "https://www.mdbg.net/chinese/export/cedict/cedict_1_0_ts_utf-8_mdbg.txt.gz".to_string()
     }
 
+    fn source(&self) -> String {
+        "cedict".to_string()
+    }
+
     fn new(_language: &Option<String>) -> anyhow::Result<Self>
     where
         Self: Sized,
@@ -1,8 +1,13 @@
-use console::Term;
+use std::io::BufRead;
+
 use rayon::prelude::*;
 use regex::Regex;
 
-use crate::{processors::traits::Extractor, progress::STYLE_PROGRESS, utils::decompress_gzip};
+use crate::{
+    processors::traits::Extractor,
+    report::{ProgressKind, Reporter},
+    utils::raw_lines,
+};
 
 use super::schema::CEDictEntry;
 
@@ -11,27 +16,30 @@ pub struct CEDictExtractor {}
 impl Extractor for CEDictExtractor {
     type Entry = CEDictEntry;
 
-    fn extract(&self, term: &Term, data: &Vec<u8>) -> anyhow::Result<Vec<CEDictEntry>> {
-        term.write_line("🔍 Extracting the dictionary...")?;
-
-        let decompressed = String::from_utf8(decompress_gzip(data)?)?;
+    fn extract<R: BufRead + Send>(
+        &self,
+        reporter: &dyn Reporter,
+        reader: R,
+    ) -> anyhow::Result<Vec<CEDictEntry>> {
+        reporter.step("Extracting the dictionary");
 
-        let lines: Vec<_> = decompressed
-            .lines()
-            .filter(|line| !line.starts_with('#') && !line.is_empty())
-            .collect();
+        let progress = reporter.progress(ProgressKind::Lines);
 
-        let progress = indicatif::ProgressBar::new(lines.len() as u64);
-        progress.set_style(STYLE_PROGRESS.clone());
+        let regex = Regex::new(r"(.*?)\s(.*?)\s\[(.*?)]\s/(.*)/")?;
 
-        let regex = Regex::new(r"(.*?)\s(.*?)\s\[(.*?)]\s/(.*)/").unwrap();
+        let results: anyhow::Result<Vec<(usize, Option<CEDictEntry>)>> = raw_lines(reader)
+            .enumerate()
+            .par_bridge()
+            .map(|(i, line)| {
+                let line = String::from_utf8_lossy(&line?).into_owned();
 
-        let results: Vec<CEDictEntry> = lines
-            .par_iter()
-            .filter_map(|line| {
                 progress.inc(1);
 
-                if let Some(captures) = regex.captures(line) {
+                if line.starts_with('#') || line.is_empty() {
+                    return Ok((i, None));
+                }
+
+                let entry = regex.captures(&line).and_then(|captures| {
                     let traditional = captures.get(1)?.as_str().to_string();
                     let simplified = captures.get(2)?.as_str().to_string();
                     let pronunciation = captures.get(3)?.as_str().to_string();
@@ -49,18 +57,22 @@ impl Extractor for CEDictExtractor {
                         pronunciation,
                         definitions,
                     })
-                } else {
-                    None
-                }
+                });
+
+                Ok((i, entry))
             })
             .collect();
 
-        progress.finish_and_clear();
+        drop(progress);
+
+        reporter.done("Extraction complete");
+
+        // Lines are parsed out of order, so restore the source order
+        let mut results = results?;
 
-        term.clear_last_lines(1)?;
-        term.write_line("✅ Extraction complete")?;
+        results.sort_unstable_by_key(|(i, _)| *i);
 
-        Ok(results)
+        Ok(results.into_iter().filter_map(|(_, entry)| entry).collect())
     }
 
     fn new() -> anyhow::Result<Self>
@@ -1,17 +1,25 @@
+//! CC-CEDICT, the community-maintained Chinese-English dictionary.
+
 use super::Processor;
 
 mod converter;
 mod downloader;
 mod extractor;
-mod schema;
 
+pub mod schema;
+
+pub use converter::CEDictConverter;
+pub use downloader::CEDictDownloader;
+pub use extractor::CEDictExtractor;
+
+/// Builds a Chinese-English dictionary from the CC-CEDICT export.
 pub struct CEDictProcessor {}
 
 impl Processor for CEDictProcessor {
     type Entry = schema::CEDictEntry;
-    type Downloader = downloader::CEDictDownloader;
-    type Extractor = extractor::CEDictExtractor;
-    type Converter = converter::CEDictConverter;
+    type Downloader = CEDictDownloader;
+    type Extractor = CEDictExtractor;
+    type Converter = CEDictConverter;
 
     fn new() -> anyhow::Result<Self>
     where
@@ -1,8 +1,10 @@
-mod cedict;
+//! The conversion pipeline of each supported source.
+
 mod traits;
 
+pub mod cedict;
 pub mod wiktionary;
 
 pub use cedict::CEDictProcessor;
-pub use traits::Processor;
-pub use wiktionary::WiktionaryProcessor;
+pub use traits::{Converter, Downloader, Extractor, Processor};
+pub use wiktionary::{WiktionaryBilingualProcessor, WiktionaryProcessor};
@@ -1,57 +1,43 @@
-use std::path::PathBuf;
+use std::{
+    io::BufRead,
+    path::{Path, PathBuf},
+};
 
+use anyhow::Context;
 use async_trait::async_trait;
-use console::Term;
 use odict::Dictionary;
 
 use crate::{
-    frequency::FrequencyMap,
-    utils::{download_with_progress, hash_url, read_file, write_file},
+    cache::Cache,
+    error::Error,
+    frequency::{FrequencyMap, FrequencySource},
+    report::Reporter,
+    utils::open_input,
 };
 
+/// Knows where the source of a dictionary is downloaded from.
 #[async_trait(?Send)]
 pub trait Downloader {
     fn new(language: &Option<String>) -> anyhow::Result<Self>
     where
         Self: Sized;
 
+    /// The URL the source is downloaded from.
     fn url(&self) -> String;
 
-    async fn download(&self, term: &Term) -> anyhow::Result<Vec<u8>> {
-        let url = self.url();
-        let data_dir = PathBuf::from(".data");
-
-        if !data_dir.exists() {
-            std::fs::create_dir_all(&data_dir)?;
-        }
-
-        let file_path = &data_dir.join(&hash_url(&url));
-
-        let content = match read_file(&file_path)? {
-            Some(content) => {
-                term.write_line(
-                    format!("✅ Using cached dictionary from {}", file_path.display()).as_str(),
-                )?;
-                content
-            }
-            None => {
-                term.write_line(format!("⬇️ Downloading the dictionary from {}...", url).as_str())?;
-
-                let content = download_with_progress(&url, &file_path).await?;
-
-                term.clear_line()?;
-                term.write_line("✅ Download complete")?;
+    /// Identifies the source in the cache index, e.g. `wiktionary/eng`.
+    fn source(&self) -> String;
 
-                write_file(&file_path, &content)?;
-
-                content
-            }
-        };
-
-        Ok(content)
+    /// Returns the path of the cached source file, downloading or
+    /// revalidating it first if needed.
+    async fn download(&self, reporter: &dyn Reporter, cache: &Cache) -> anyhow::Result<PathBuf> {
+        cache
+            .fetch(&self.url(), "dictionary", &self.source(), reporter)
+            .await
     }
 }
 
+/// Parses the entries of a source file.
 pub trait Extractor {
     type Entry;
 
@@ -59,9 +45,14 @@ pub trait Extractor {
     where
         Self: Sized;
 
-    fn extract(&self, term: &Term, data: &Vec<u8>) -> anyhow::Result<Vec<Self::Entry>>;
+    fn extract<R: BufRead + Send>(
+        &self,
+        reporter: &dyn Reporter,
+        reader: R,
+    ) -> anyhow::Result<Vec<Self::Entry>>;
 }
 
+/// Turns extracted entries into a dictionary.
 pub trait Converter {
     type Entry;
 
@@ -69,15 +60,22 @@ pub trait Converter {
     where
         Self: Sized;
 
+    /// Converts `data` into the dictionary of `language`. Its ID is derived
+    /// from the source and language with
+    /// [`dictionary_id`](crate::utils::dictionary_id), so rebuilding it
+    /// gives the same ID.
     fn convert(
         &mut self,
-        term: &Term,
+        reporter: &dyn Reporter,
         frequency_map: &Option<FrequencyMap>,
-        data: &Vec<Self::Entry>,
+        data: &[Self::Entry],
         language: Option<String>,
     ) -> anyhow::Result<Dictionary>;
 }
 
+/// Ties a source's [`Downloader`], [`Extractor`] and [`Converter`] together
+/// into the whole pipeline.
+#[async_trait(?Send)]
 pub trait Processor {
     type Entry;
 
@@ -89,20 +87,91 @@ pub trait Processor {
     where
         Self: Sized;
 
-    async fn process(&self, term: &Term, language: Option<String>) -> anyhow::Result<Dictionary> {
-        let downloader = Self::Downloader::new(&language)?;
-        let extractor = Self::Extractor::new()?;
-        let mut converter = Self::Converter::new()?;
+    /// Creates the downloader for `language`.
+    fn downloader(&self, language: &Option<String>) -> anyhow::Result<Self::Downloader> {
+        Self::Downloader::new(language)
+    }
 
+    /// Where the frequencies `language` is ranked by come from, unless
+    /// chosen otherwise.
+    fn frequency_source(&self, _language: &str) -> FrequencySource {
+        FrequencySource::Auto
+    }
+
+    /// Creates the extractor `build` uses, which processors with options
+    /// override to configure it.
+    fn extractor(&self) -> anyhow::Result<Self::Extractor> {
+        Self::Extractor::new()
+    }
+
+    /// Creates the converter `build` uses.
+    fn converter(&self) -> anyhow::Result<Self::Converter> {
+        Self::Converter::new()
+    }
+
+    /// Downloads and builds the dictionary for `language`, reading the
+    /// source from `input` instead if given.
+    async fn process(
+        &self,
+        reporter: &dyn Reporter,
+        cache: &Cache,
+        language: Option<String>,
+        input: Option<PathBuf>,
+    ) -> anyhow::Result<Dictionary> {
         let frequency_map = match &language {
-            Some(lang) => FrequencyMap::new(lang, term).await?,
+            Some(lang) => {
+                FrequencyMap::from_source(self.frequency_source(lang), lang, reporter, cache)
+                    .await?
+            }
             None => None,
         };
 
-        let data = downloader.download(term).await?;
-        let parsed = extractor.extract(term, &data)?;
-        let dictionary = converter.convert(term, &frequency_map, &parsed, language)?;
+        let path = self.fetch(reporter, cache, &language, input).await?;
 
-        Ok(dictionary)
+        self.build(reporter, &frequency_map, &path, language)
+    }
+
+    /// Returns the path of the source file for `language`, which is `input`
+    /// if given and a cached download otherwise.
+    async fn fetch(
+        &self,
+        reporter: &dyn Reporter,
+        cache: &Cache,
+        language: &Option<String>,
+        input: Option<PathBuf>,
+    ) -> anyhow::Result<PathBuf> {
+        let downloader = self.downloader(language)?;
+
+        match input {
+            Some(path) => cache.use_local(
+                &path,
+                &downloader.url(),
+                "dictionary",
+                &downloader.source(),
+                reporter,
+            ),
+            None => downloader.download(reporter, cache).await,
+        }
+    }
+
+    /// Extracts and converts the source file at `path`.
+    fn build(
+        &self,
+        reporter: &dyn Reporter,
+        frequency_map: &Option<FrequencyMap>,
+        path: &Path,
+        language: Option<String>,
+    ) -> anyhow::Result<Dictionary> {
+        let extractor = self.extractor()?;
+        let mut converter = self.converter()?;
+
+        let reader = open_input(path)?;
+        let parsed = extractor
+            .extract(reporter, reader)
+            .with_context(|| format!("Failed to extract {}", path.display()))?;
+
+        converter
+            .convert(reporter, frequency_map, &parsed, language)
+            .map_err(|e| Error::Convert { source: e }.into())
     }
 }
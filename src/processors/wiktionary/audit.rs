@@ -0,0 +1,293 @@
+//! Finds where kaikki records have drifted from the structs in
+//! [`schema`](super::schema): keys the structs don't declare, and values of
+//! a different type than the structs expect.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+    sync::{
+        Mutex, PoisonError,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::report::Reporter;
+
+use super::schema::WiktionaryEntry;
+
+/// How many mismatched values are cut out of a record before giving up on
+/// the rest of it.
+const MAX_MISMATCHES: usize = 64;
+
+/// Counts unknown keys and type mismatches per schema struct over every
+/// record it is shown.
+pub struct SchemaAudit {
+    records: AtomicUsize,
+    findings: Mutex<HashMap<Finding, usize>>,
+    literal: Regex,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct Finding {
+    schema: &'static str,
+    key: String,
+    problem: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+impl SchemaAudit {
+    pub fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            records: AtomicUsize::new(0),
+            findings: Mutex::default(),
+            literal: Regex::new(r#"\s*(`[^`]*`|"[^"]*")"#)?,
+        })
+    }
+
+    /// Audits one JSONL record. Lines that aren't JSON at all are left to
+    /// the extractor to report.
+    pub fn record(&self, line: &str) {
+        if let Ok(value) = serde_json::from_str::<Value>(line) {
+            self.record_value(value);
+        }
+    }
+
+    /// Audits one record that has already been parsed.
+    pub fn record_value(&self, mut value: Value) {
+        self.records.fetch_add(1, Ordering::Relaxed);
+
+        // Findings are counted once per record, however often they occur in
+        // it and however often the record is deserialized again
+        let mut found: HashSet<Finding> = HashSet::new();
+        let mut removed: Vec<Vec<Step>> = vec![];
+
+        for _ in 0..MAX_MISMATCHES {
+            let mut ignored = vec![];
+            let mut callback = |path: serde_ignored::Path| ignored.push(ignored_steps(&path));
+            let result = serde_path_to_error::deserialize::<_, WiktionaryEntry>(
+                serde_ignored::Deserializer::new(&value, &mut callback),
+            );
+
+            for steps in ignored {
+                let (schema, key) = locate(&steps);
+
+                found.insert(Finding {
+                    schema,
+                    key,
+                    problem: "unknown key".to_string(),
+                });
+            }
+
+            let Err(error) = result else {
+                break;
+            };
+
+            let mut steps: Vec<Step> = error
+                .path()
+                .iter()
+                .filter_map(|segment| match segment {
+                    serde_path_to_error::Segment::Map { key } => Some(Step::Key(key.clone())),
+                    serde_path_to_error::Segment::Seq { index } => Some(Step::Index(*index)),
+                    _ => None,
+                })
+                .collect();
+
+            let message = error.inner().to_string();
+            let problem = self.literal.replace_all(&message, "").to_string();
+
+            // A missing field is reported at the struct that lacks it, and
+            // there is nothing to cut out to get past it
+            let missing = message
+                .strip_prefix("missing field `")
+                .and_then(|rest| rest.split('`').next());
+
+            if let Some(field) = missing {
+                steps.push(Step::Key(field.to_string()));
+            }
+
+            // ...unless it's missing because it was cut out as a mismatch
+            if !removed.contains(&steps) {
+                let (schema, key) = locate(&steps);
+
+                found.insert(Finding {
+                    schema,
+                    key,
+                    problem,
+                });
+            }
+
+            if missing.is_some() || !remove(&mut value, &steps) {
+                break;
+            }
+
+            removed.push(steps);
+        }
+
+        if found.is_empty() {
+            return;
+        }
+
+        let mut findings = self.findings.lock().unwrap_or_else(PoisonError::into_inner);
+
+        for finding in found {
+            *findings.entry(finding).or_default() += 1;
+        }
+    }
+
+    /// Renders the findings as a table, most frequent first, or `None` if
+    /// every record matched the schema.
+    pub fn table(&self) -> Option<String> {
+        let findings = self.findings.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if findings.is_empty() {
+            return None;
+        }
+
+        let mut rows: Vec<(String, &str, usize)> = findings
+            .iter()
+            .map(|(f, count)| {
+                (
+                    format!("{}.{}", f.schema, f.key),
+                    f.problem.as_str(),
+                    *count,
+                )
+            })
+            .collect();
+
+        rows.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)).then(a.1.cmp(b.1)));
+
+        let key_width = rows.iter().map(|(key, _, _)| key.len()).max().unwrap_or(0);
+        let count_width = rows[0].2.to_string().len().max(7);
+
+        let mut table = format!(
+            "Schema audit of {} records found {} unknown keys and type mismatches:\n",
+            self.records.load(Ordering::Relaxed),
+            rows.len()
+        );
+
+        let _ = write!(
+            table,
+            "{:>count_width$}  {:<key_width$}  problem",
+            "records", "field"
+        );
+
+        for (key, problem, count) in rows {
+            let _ = write!(
+                table,
+                "\n{:>count_width$}  {:<key_width$}  {}",
+                count, key, problem
+            );
+        }
+
+        Some(table)
+    }
+
+    /// Reports the table, if there is anything in it.
+    pub fn report(&self, reporter: &dyn Reporter) {
+        match self.table() {
+            Some(table) => reporter.warn(&table),
+            None => reporter.done(&format!(
+                "Schema audit of {} records found no unknown keys or type mismatches",
+                self.records.load(Ordering::Relaxed)
+            )),
+        }
+    }
+}
+
+/// The struct a field of the struct `schema` deserializes into, if any.
+fn child(schema: &str, field: &str) -> Option<&'static str> {
+    let child = match (schema, field) {
+        ("WiktionaryEntry", "senses") => "WordSense",
+        ("WiktionaryEntry", "forms") => "WordForm",
+        ("WiktionaryEntry", "sounds") => "Sound",
+        ("WiktionaryEntry", "descendants") => "Descendant",
+        ("WiktionaryEntry", "etymology_templates" | "head_templates" | "inflection_templates")
+        | ("Descendant", "templates") => "Template",
+        ("WiktionaryEntry" | "WordSense", "translations") => "Translation",
+        (
+            "WiktionaryEntry" | "WordSense",
+            "synonyms" | "antonyms" | "hypernyms" | "hyponyms" | "holonyms" | "meronyms"
+            | "derived" | "related" | "coordinate_terms",
+        ) => "WordLink",
+        ("WordSense", "categories") => "WordCategory",
+        ("WordSense", "alt_of" | "form_of") => "FormOf",
+        ("WordSense", "examples") => "Example",
+        _ => return None,
+    };
+
+    Some(child)
+}
+
+/// Returns the struct `steps` ends in and the key of that struct it names.
+fn locate(steps: &[Step]) -> (&'static str, String) {
+    let mut schema = "WiktionaryEntry";
+    let mut keys = steps.iter().filter_map(|step| match step {
+        Step::Key(key) => Some(key.as_str()),
+        Step::Index(_) => None,
+    });
+
+    loop {
+        match keys.next() {
+            Some(key) => match child(schema, key) {
+                Some(next) => schema = next,
+                None => return (schema, key.to_string()),
+            },
+            None => return (schema, String::new()),
+        }
+    }
+}
+
+fn ignored_steps(path: &serde_ignored::Path) -> Vec<Step> {
+    let (parent, step) = match path {
+        serde_ignored::Path::Root => return vec![],
+        serde_ignored::Path::Seq { parent, index } => (parent, Some(Step::Index(*index))),
+        serde_ignored::Path::Map { parent, key } => (parent, Some(Step::Key(key.clone()))),
+        serde_ignored::Path::Some { parent }
+        | serde_ignored::Path::NewtypeStruct { parent }
+        | serde_ignored::Path::NewtypeVariant { parent } => (parent, None),
+    };
+
+    let mut steps = ignored_steps(parent);
+
+    steps.extend(step);
+    steps
+}
+
+/// Cuts the value at `steps` out of `value`, returning whether there was one.
+fn remove(value: &mut Value, steps: &[Step]) -> bool {
+    let Some((last, parents)) = steps.split_last() else {
+        return false;
+    };
+
+    let mut current = value;
+
+    for step in parents {
+        let next = match (step, current) {
+            (Step::Key(key), Value::Object(map)) => map.get_mut(key),
+            (Step::Index(index), Value::Array(array)) => array.get_mut(*index),
+            _ => None,
+        };
+
+        match next {
+            Some(next) => current = next,
+            None => return false,
+        }
+    }
+
+    match (last, current) {
+        (Step::Key(key), Value::Object(map)) => map.remove(key).is_some(),
+        (Step::Index(index), Value::Array(array)) if *index < array.len() => {
+            array.remove(*index);
+            true
+        }
+        _ => false,
+    }
+}
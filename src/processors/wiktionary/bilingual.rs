@@ -0,0 +1,398 @@
+//! Bilingual dictionaries from another language into the language of a
+//! Wiktionary edition, made by inverting the edition's translation tables.
+//! The English Wiktionary translates its English words into hundreds of
+//! languages, which gives a dictionary for each of them, however poorly
+//! Wiktionary covers the language itself.
+
+use std::collections::HashMap;
+
+use odict::{
+    Definition, DefinitionType, Dictionary, Entry, Etymology, Group, PartOfSpeech, Pronunciation,
+    PronunciationKind, Sense,
+};
+
+use crate::{
+    frequency::{FrequencyMap, FrequencySource},
+    processors::traits::{Converter, Processor},
+    report::{ProgressKind, Reporter},
+    utils::dictionary_id,
+};
+
+use super::{
+    Edition, Languages, WiktionaryConverter, WiktionaryDownloader, WiktionaryExtractor,
+    WiktionaryOptions, converter::same_gloss, schema::WiktionaryEntry,
+};
+
+/// Builds a dictionary of one language into the language of a Wiktionary
+/// edition from the translations of that edition's own words.
+pub struct WiktionaryBilingualProcessor {
+    options: WiktionaryOptions,
+    languages: Languages,
+}
+
+impl WiktionaryBilingualProcessor {
+    /// Creates the processor for dictionaries into `target`, which must be
+    /// the language of a Wiktionary edition.
+    pub fn with_options(mut options: WiktionaryOptions, target: &str) -> anyhow::Result<Self> {
+        let languages = Languages::load(options.profiles.as_deref())?;
+        let target = languages.profile(target)?;
+
+        let Some(edition) = Edition::for_gloss_language(&target.code) else {
+            anyhow::bail!(
+                "Bilingual dictionaries can't be built into {}, only into English, French or German",
+                target.name
+            );
+        };
+
+        options.edition = edition;
+
+        Ok(Self { options, languages })
+    }
+}
+
+impl Processor for WiktionaryBilingualProcessor {
+    type Entry = WiktionaryEntry;
+    type Downloader = WiktionaryDownloader;
+    type Extractor = WiktionaryExtractor;
+    type Converter = WiktionaryBilingualConverter;
+
+    fn new() -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::with_options(WiktionaryOptions::default(), "eng")
+    }
+
+    /// The translations are read from the target language's own entries,
+    /// whatever the language of the dictionary.
+    fn downloader(&self, _language: &Option<String>) -> anyhow::Result<WiktionaryDownloader> {
+        let edition = self.options.edition;
+
+        Ok(WiktionaryDownloader::with_profile(
+            self.languages.profile(edition.gloss_language())?,
+            edition,
+        ))
+    }
+
+    fn frequency_source(&self, language: &str) -> FrequencySource {
+        self.languages
+            .profile(language)
+            .map_or(FrequencySource::Auto, |profile| profile.frequency)
+    }
+
+    fn extractor(&self) -> anyhow::Result<WiktionaryExtractor> {
+        WiktionaryExtractor::with_options(&self.options)
+    }
+
+    fn converter(&self) -> anyhow::Result<WiktionaryBilingualConverter> {
+        WiktionaryBilingualConverter::with_options(&self.options, self.languages.clone())
+    }
+}
+
+/// Inverts translation tables: every translation into the dictionary's
+/// language becomes a headword, defined by the words it translates, grouped
+/// by the gloss of the sense they translate.
+pub struct WiktionaryBilingualConverter {
+    edition: Edition,
+    languages: Languages,
+    /// Resolves the parts of speech and translation languages of the
+    /// entries the translations are read from
+    resolver: WiktionaryConverter,
+}
+
+/// What is known of a headword of the dictionary so far.
+#[derive(Default)]
+struct Inverted {
+    romanizations: Vec<String>,
+    senses: Vec<(PartOfSpeech, InvertedSense)>,
+}
+
+#[derive(Default)]
+struct InvertedSense {
+    tags: Vec<String>,
+    /// The words translated, grouped by the gloss of the sense they
+    /// translate, if known
+    glosses: Vec<(Option<String>, Vec<String>)>,
+}
+
+impl WiktionaryBilingualConverter {
+    pub fn with_options(options: &WiktionaryOptions, languages: Languages) -> anyhow::Result<Self> {
+        Ok(Self {
+            edition: options.edition,
+            resolver: WiktionaryConverter::with_options(options, languages.clone())?,
+            languages,
+        })
+    }
+}
+
+impl Converter for WiktionaryBilingualConverter {
+    type Entry = WiktionaryEntry;
+
+    fn convert(
+        &mut self,
+        reporter: &dyn Reporter,
+        frequency_map: &Option<FrequencyMap>,
+        data: &[WiktionaryEntry],
+        language: Option<String>,
+    ) -> anyhow::Result<Dictionary> {
+        let Some(language) = language else {
+            anyhow::bail!("A language is required for bilingual Wiktionary dictionaries");
+        };
+
+        reporter.step("Inverting the translations");
+
+        let profile = self.languages.profile(&language)?;
+        let normalizer = profile.normalize.compile()?;
+
+        self.resolver.start(Some(self.edition.gloss_language()))?;
+
+        let progress = reporter.progress(ProgressKind::Items(data.len() as u64));
+        let mut order: Vec<String> = vec![];
+        let mut inverted: HashMap<String, Inverted> = HashMap::new();
+
+        for entry in data {
+            let pos = self.resolver.resolve_pos(entry);
+
+            // Translations of a sense translate its (most specific) gloss,
+            // translations of the whole entry name the sense they translate
+            let translations = entry
+                .senses
+                .iter()
+                .flat_map(|sense| {
+                    sense
+                        .translations
+                        .iter()
+                        .map(move |t| (sense.glosses.last().cloned(), t))
+                })
+                .chain(entry.translations.iter().map(|t| (t.sense.clone(), t)));
+
+            for (gloss, translation) in translations {
+                let Some(word) = translation.word.as_deref().filter(|w| !w.is_empty()) else {
+                    continue;
+                };
+
+                if self.resolver.translation_language(translation).as_ref() != Some(&profile.code) {
+                    continue;
+                }
+
+                let term = normalizer.apply(word);
+                let headword = inverted.entry(term.clone()).or_insert_with(|| {
+                    order.push(term);
+                    Inverted::default()
+                });
+
+                if let Some(roman) = &translation.roman
+                    && !headword.romanizations.contains(roman)
+                {
+                    headword.romanizations.push(roman.clone());
+                }
+
+                let sense = entry_in(&mut headword.senses, pos.clone(), |a, b| a == b);
+
+                for tag in &translation.tags {
+                    if !sense.tags.contains(tag) {
+                        sense.tags.push(tag.clone());
+                    }
+                }
+
+                let words = entry_in(&mut sense.glosses, gloss, |a, b| match (a, b) {
+                    (Some(a), Some(b)) => same_gloss(a, b),
+                    (a, b) => a == b,
+                });
+
+                if !words.contains(&entry.word) {
+                    words.push(entry.word.clone());
+                }
+            }
+
+            progress.inc(1);
+        }
+
+        drop(progress);
+
+        reporter.done(&format!(
+            "Inverted the translations into {} headwords",
+            inverted.len()
+        ));
+
+        self.resolver.report_missing_pos(reporter);
+
+        let entries = order
+            .into_iter()
+            .map(|term| {
+                let headword = inverted.remove(&term).unwrap_or_default();
+
+                Entry {
+                    rank: frequency_map.as_ref().and_then(|m| m.get_frequency(&term)),
+                    term,
+                    see_also: None,
+                    media: vec![],
+                    etymologies: vec![Etymology {
+                        id: None,
+                        description: None,
+                        pronunciations: headword
+                            .romanizations
+                            .into_iter()
+                            .map(|roman| Pronunciation {
+                                kind: Some(PronunciationKind::Other("romanization".to_string())),
+                                value: roman,
+                                media: vec![],
+                            })
+                            .collect(),
+                        senses: headword
+                            .senses
+                            .into_iter()
+                            .map(|(pos, sense)| inverted_sense(pos, sense))
+                            .collect(),
+                    }],
+                }
+            })
+            .collect();
+
+        Ok(Dictionary {
+            id: dictionary_id(
+                &self.edition.source(self.edition.gloss_language()),
+                Some(&profile.code),
+            )?,
+            name: Some(format!(
+                "{}-{} Wiktionary translations",
+                profile.name,
+                self.edition.gloss_name()
+            )),
+            entries,
+        })
+    }
+
+    fn new() -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::with_options(&WiktionaryOptions::default(), Languages::builtin()?)
+    }
+}
+
+/// The value for `key` in a list of pairs kept in insertion order, added
+/// with its default if no key is the `same` yet.
+fn entry_in<K, V: Default>(
+    list: &mut Vec<(K, V)>,
+    key: K,
+    same: impl Fn(&K, &K) -> bool,
+) -> &mut V {
+    let index = match list.iter().position(|(k, _)| same(k, &key)) {
+        Some(index) => index,
+        None => {
+            list.push((key, V::default()));
+            list.len() - 1
+        }
+    };
+
+    &mut list[index].1
+}
+
+fn inverted_sense(pos: PartOfSpeech, sense: InvertedSense) -> Sense {
+    let definitions = sense
+        .glosses
+        .into_iter()
+        .flat_map(|(gloss, words)| {
+            let definitions = words.into_iter().map(|word| Definition {
+                id: None,
+                value: word,
+                examples: vec![],
+                notes: vec![],
+            });
+
+            match gloss {
+                Some(gloss) => vec![DefinitionType::Group(Group {
+                    id: None,
+                    description: gloss,
+                    definitions: definitions.collect(),
+                })],
+                None => definitions.map(DefinitionType::Definition).collect(),
+            }
+        })
+        .collect();
+
+    Sense {
+        pos,
+        lemma: None,
+        tags: sense.tags,
+        translations: vec![],
+        forms: vec![],
+        definitions,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Silent;
+
+    #[test]
+    fn inverts_translations_into_merged_senses() {
+        let entries: Vec<WiktionaryEntry> = [
+            r#"{"word": "dog", "lang": "English", "lang_code": "en", "pos": "noun",
+                "senses": [{"glosses": ["A mammal"], "translations": [
+                    {"lang": "Russian", "code": "ru", "word": "собака", "roman": "sobaka",
+                     "tags": ["feminine"]},
+                    {"lang": "French", "code": "fr", "word": "chien"}]}]}"#,
+            r#"{"word": "hound", "lang": "English", "lang_code": "en", "pos": "noun",
+                "senses": [{"glosses": ["A mammal."]}, {"glosses": ["A hunting dog"]}],
+                "translations": [
+                    {"lang": "Russian", "code": "ru", "word": "собака", "sense": "a mammal"},
+                    {"lang": "Russian", "code": "ru", "word": "собака", "sense": "A hunting dog",
+                     "tags": ["animate"]}]}"#,
+            r#"{"word": "dog", "lang": "English", "lang_code": "en", "pos": "verb",
+                "senses": [{"glosses": ["To follow"], "translations": [
+                    {"lang": "Russian", "code": "ru", "word": "собака"}]}]}"#,
+        ]
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+
+        let dictionary = WiktionaryBilingualConverter::new()
+            .unwrap()
+            .convert(&Silent, &None, &entries, Some("rus".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            dictionary
+                .entries
+                .iter()
+                .map(|e| e.term.as_str())
+                .collect::<Vec<_>>(),
+            ["собака"]
+        );
+
+        let ety = &dictionary.entries.iter().next().unwrap().etymologies[0];
+        let noun = ety.senses.get(&PartOfSpeech::N).unwrap();
+        let groups: Vec<(&str, Vec<&str>)> = noun
+            .definitions
+            .iter()
+            .map(|definition| match definition {
+                DefinitionType::Group(group) => (
+                    group.description.as_str(),
+                    group.definitions.iter().map(|d| d.value.as_str()).collect(),
+                ),
+                DefinitionType::Definition(_) => unreachable!(),
+            })
+            .collect();
+
+        // Translations of the same sense of different words are merged
+        assert_eq!(
+            groups,
+            [
+                ("A mammal", vec!["dog", "hound"]),
+                ("A hunting dog", vec!["hound"])
+            ]
+        );
+        assert_eq!(noun.tags, ["feminine", "animate"]);
+        assert_eq!(ety.senses.len(), 2);
+        assert_eq!(
+            ety.pronunciations
+                .iter()
+                .map(|p| p.value.as_str())
+                .collect::<Vec<_>>(),
+            ["sobaka"]
+        );
+    }
+}
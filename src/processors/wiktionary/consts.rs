@@ -2,24 +2,7 @@ use map_macro::hash_map;
 use odict::PartOfSpeech;
 use std::{collections::HashMap, sync::LazyLock};
 
-pub const SUPPORTED_LANGUAGES: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
-    hash_map! {
-        "eng" => "English",
-        "fra" => "French",
-        "ger" => "German",
-        "ita" => "Italian",
-        "por" => "Portuguese",
-        "pol" => "Polish",
-        "spa" => "Spanish",
-        "swe" => "Swedish",
-        "jpn" => "Japanese",
-        "rus" => "Russian",
-        "ara" => "Arabic",
-        "cmn" => "Chinese"
-    }
-});
-
-pub const POS_MAP: LazyLock<HashMap<&str, PartOfSpeech>> = LazyLock::new(|| {
+pub static POS_MAP: LazyLock<HashMap<&str, PartOfSpeech>> = LazyLock::new(|| {
     hash_map! {
         // Core parts of speech
         "adj" => PartOfSpeech::Adj,
@@ -33,6 +16,11 @@ pub const POS_MAP: LazyLock<HashMap<&str, PartOfSpeech>> = LazyLock::new(|| {
         "det" => PartOfSpeech::Det,
         "num" => PartOfSpeech::Num,
         "name" => PartOfSpeech::Propn,
+        "postp" => PartOfSpeech::Postp,
+        "classifier" => PartOfSpeech::Cls,
+        "counter" => PartOfSpeech::Ctr,
+        "adj_noun" => PartOfSpeech::AdjNa,
+        "adnominal" => PartOfSpeech::AdjPn,
 
         // Abbreviations and contractions
         "abbrev" => PartOfSpeech::Abv,
@@ -1,34 +1,163 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::{frequency::FrequencyMap, processors::traits::Converter, progress::STYLE_PROGRESS};
-use console::Term;
+use crate::{
+    frequency::FrequencyMap,
+    processors::traits::Converter,
+    report::{ProgressKind, Reporter},
+    utils::dictionary_id,
+};
 use map_macro::{hash_map, hash_set};
 use odict::{
-    Definition, DefinitionType, Dictionary, Entry, EntryRef, Etymology, Form, Group, ID, MediaURL,
-    PartOfSpeech, Pronunciation, PronunciationKind, Sense,
+    Definition, DefinitionType, Dictionary, Entry, EntryRef, Etymology, Example, Form, Group, ID,
+    MediaURL, Note, PartOfSpeech, Pronunciation, PronunciationKind, Sense, Translation,
 };
 
 use super::{
-    SUPPORTED_LANGUAGES,
+    Edition, LanguageProfile, Languages, Normalizer, PosMap, WiktionaryOptions,
     consts::POS_MAP,
-    schema::{Sound, WiktionaryEntry},
+    forms::{form_kind, is_pseudo_form},
+    schema::{self, LEMMA_KINDS, RELATIONS, Sound, WiktionaryEntry, WordLink, WordSense},
 };
 
+/// Converts the entries of one language. Entries can be handed over all at
+/// once with [`Converter::convert`], or one at a time with
+/// [`start`](Self::start), [`add`](Self::add) and [`finish`](Self::finish).
 pub struct WiktionaryConverter {
-    missing_pos: Vec<String>,
+    edition: Edition,
+    languages: Languages,
+    pos_map: PosMap,
+    missing_pos: HashMap<String, usize>,
+    max_examples: Option<usize>,
+    skip_quotations: bool,
+    translation_languages: HashSet<String>,
+    form_redirects: bool,
+    /// The ISO 639-3 codes of the Wiktionary language codes seen so far
+    language_codes: HashMap<String, Option<String>>,
+    profile: Option<LanguageProfile>,
+    normalizer: Normalizer,
+    entries: HashMap<String, Entry>,
 }
 
 impl WiktionaryConverter {
-    fn resolve_pos<'a>(&mut self, entry: &WiktionaryEntry) -> PartOfSpeech {
+    pub fn with_options(options: &WiktionaryOptions, languages: Languages) -> anyhow::Result<Self> {
+        let pos_map = match &options.pos_map {
+            Some(path) => PosMap::load(path)?,
+            None => PosMap::default(),
+        };
+
+        let translation_languages = options
+            .translation_languages
+            .iter()
+            .map(|code| Ok(languages.profile(code)?.code))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            edition: options.edition,
+            translation_languages,
+            language_codes: HashMap::new(),
+            languages,
+            pos_map,
+            missing_pos: HashMap::new(),
+            max_examples: options.max_examples,
+            skip_quotations: options.skip_quotations,
+            form_redirects: options.form_redirects,
+            profile: None,
+            normalizer: Normalizer::default(),
+            entries: HashMap::new(),
+        })
+    }
+
+    /// Starts a new dictionary of `language`, dropping anything added so far.
+    pub fn start(&mut self, language: Option<&str>) -> anyhow::Result<()> {
+        self.profile = language
+            .map(|language| self.languages.profile(language))
+            .transpose()?;
+        self.normalizer = self
+            .profile
+            .as_ref()
+            .map(|profile| profile.normalize.compile())
+            .transpose()?
+            .unwrap_or_default();
+        self.missing_pos.clear();
+        self.entries.clear();
+
+        Ok(())
+    }
+
+    /// Returns the dictionary of everything added since [`start`](Self::start),
+    /// ranked by `frequency_map`.
+    pub fn finish(
+        &mut self,
+        reporter: &dyn Reporter,
+        frequency_map: &Option<FrequencyMap>,
+    ) -> anyhow::Result<Dictionary> {
+        self.report_missing_pos(reporter);
+
+        let mut entries = std::mem::take(&mut self.entries);
+
+        if self.form_redirects {
+            let redirects = form_redirects(&entries);
+
+            reporter.done(&format!(
+                "Added {} redirect entries for inflected forms",
+                redirects.len()
+            ));
+
+            entries.extend(redirects);
+        }
+
+        let terms: HashSet<String> = entries.keys().cloned().collect();
+
+        for entry in entries.values_mut() {
+            prune_links(entry, &terms);
+        }
+
+        let entries = entries
+            .into_values()
+            .map(|entry| Entry {
+                rank: frequency_map
+                    .as_ref()
+                    .and_then(|m| m.get_frequency(&entry.term)),
+                ..entry
+            })
+            .collect();
+
+        // Dictionaries are identified by where they come from, like their
+        // downloads are
+        let id = match &self.profile {
+            Some(profile) => {
+                dictionary_id(&self.edition.source(&profile.code), Some(&profile.code))?
+            }
+            None => ID::new(),
+        };
+
+        Ok(Dictionary {
+            id,
+            entries,
+            name: self
+                .profile
+                .as_ref()
+                .map(|profile| self.edition.dictionary_name(&profile.name)),
+        })
+    }
+
+    pub(super) fn resolve_pos(&mut self, entry: &WiktionaryEntry) -> PartOfSpeech {
+        let profile = self.profile.as_ref();
+
         if let Some(pos_value) = &entry.pos {
-            // Try to get the mapped PartOfSpeech from POS_MAP
-            if let Some(resolved_pos) = POS_MAP.get(pos_value.as_str()).cloned() {
-                return resolved_pos;
+            // The POS map takes precedence over the language profile, which
+            // takes precedence over the built-in map
+            let resolved_pos = self
+                .pos_map
+                .get(profile.map(|p| p.code.as_str()), pos_value)
+                .or_else(|| profile.and_then(|p| p.pos.get(pos_value)))
+                .or_else(|| POS_MAP.get(pos_value.as_str()));
+
+            if let Some(resolved_pos) = resolved_pos {
+                return resolved_pos.clone();
             } else {
                 // If not found in the map, use PartOfSpeech::Other with the original value
-                if !self.missing_pos.contains(pos_value) {
-                    self.missing_pos.push(pos_value.clone());
-                }
+                *self.missing_pos.entry(pos_value.clone()).or_default() += 1;
                 return PartOfSpeech::Other(pos_value.clone());
             }
         }
@@ -36,16 +165,410 @@ impl WiktionaryConverter {
         // Default to Unknown if no POS is provided
         PartOfSpeech::Un
     }
+
+    /// Adds an entry to the dictionary being built.
+    pub fn add(&mut self, entry: &WiktionaryEntry) {
+        let pos = self.resolve_pos(entry);
+        let term = self.normalizer.apply(&entry.word);
+        let see_also = entry
+            .redirects
+            .as_ref()
+            .and_then(|r| r.first())
+            .map(|r| self.normalizer.apply(r));
+        let etymology_text = entry.etymology_text.to_owned();
+        let pronunciations = entry
+            .sounds
+            .iter()
+            .map(|s| s.to_owned())
+            .filter_map(|s| s.into())
+            .collect::<Vec<odict::Pronunciation>>();
+
+        let mut definitions: Vec<DefinitionType> = vec![];
+        let mut group_map: HashMap<String, usize> = hash_map! {};
+
+        let mut lemma: Option<EntryRef> = None;
+        let mut tags: Vec<String> = vec![];
+
+        for sense in &entry.senses {
+            tags.extend(sense.tags.iter().cloned());
+
+            // The sense's lemma is the first one it is an inflection of,
+            // every lemma is linked from its definition
+            if lemma.is_none()
+                && let Some(fo) = sense.form_of.first()
+            {
+                lemma = Some(EntryRef::from(self.normalizer.apply(&fo.word)));
+            }
+
+            let lemma_links = self.lemma_links(sense);
+            let references = self.cross_references(sense.relations());
+
+            // A sense without glosses, like many form-of senses, has no
+            // definition to link from, so its sense links instead, the way
+            // relations of a whole entry do
+            if sense.glosses.is_empty() {
+                for link in lemma_links
+                    .into_iter()
+                    .map(|note| note.value)
+                    .chain(references)
+                {
+                    if !tags.contains(&link) {
+                        tags.push(link);
+                    }
+                }
+
+                continue;
+            }
+
+            let (examples, mut notes) = self.examples(sense);
+
+            notes.extend(lemma_links);
+            notes.extend(references.into_iter().map(|value| Note {
+                id: None,
+                value,
+                examples: vec![],
+            }));
+
+            // Glosses with 2 senses typically have subdefinitions
+            if sense.glosses.len() == 2 {
+                let parent = sense.glosses[0].to_owned();
+                let child = sense.glosses[1].to_owned();
+                let definition = Definition {
+                    id: None,
+                    value: child.to_owned(),
+                    examples,
+                    notes,
+                };
+
+                if let Some(&idx) = group_map.get(&parent) {
+                    if let DefinitionType::Group(group) = &mut definitions[idx] {
+                        group.definitions.push(definition);
+                    }
+                } else {
+                    let group = DefinitionType::Group(Group {
+                        id: None,
+                        description: parent.to_owned(),
+                        definitions: vec![definition],
+                    });
+                    definitions.push(group);
+                    group_map.insert(parent, definitions.len() - 1);
+                }
+            } else if let Some(gloss) = sense.glosses.first() {
+                let definition = Definition {
+                    id: None,
+                    value: gloss.to_owned(),
+                    examples,
+                    notes,
+                };
+                definitions.push(DefinitionType::Definition(definition));
+            }
+        }
+
+        for reference in self.cross_references(entry.relations()) {
+            if !tags.contains(&reference) {
+                tags.push(reference);
+            }
+        }
+
+        let etymology_number = entry.etymology_number.unwrap_or(1);
+
+        // Pronunciations and romanizations of forms are kept as tags, e.g.
+        // "ipa:/dɒɡz/", as odict forms have no place of their own for them
+        let forms = entry
+            .forms
+            .iter()
+            .filter(|f| !is_pseudo_form(f))
+            .map(|f| Form {
+                kind: form_kind(&f.tags),
+                term: EntryRef::from(self.normalizer.apply(&f.form)),
+                tags: f
+                    .tags
+                    .iter()
+                    .cloned()
+                    .chain(f.ipa.iter().map(|ipa| format!("ipa:{}", ipa)))
+                    .chain(f.roman.iter().map(|roman| format!("roman:{}", roman)))
+                    .collect(),
+            })
+            .collect();
+
+        let sense = Sense {
+            pos: pos.to_owned(),
+            lemma: lemma.to_owned(),
+            tags,
+            translations: self.translations(entry),
+            forms,
+            definitions,
+        };
+
+        // Senses are told apart by their part of speech only, so a record
+        // with the same etymology and part of speech as an earlier one adds
+        // to the sense that is already there
+        if let Some(ety) = self.entries.get_mut(term.as_str()).and_then(|e| {
+            e.etymologies
+                .get_mut((etymology_number as usize).saturating_sub(1))
+        }) {
+            let sense = match ety.senses.take(&pos) {
+                Some(existing) => merge_senses(existing, sense),
+                None => sense,
+            };
+
+            ety.senses.insert(sense);
+
+            for pronunciation in pronunciations {
+                if !ety.pronunciations.contains(&pronunciation) {
+                    ety.pronunciations.push(pronunciation);
+                }
+            }
+
+            if ety.description.is_none() {
+                ety.description = etymology_text;
+            }
+        } else {
+            let ety = Etymology {
+                id: None,
+                pronunciations,
+                description: etymology_text.to_owned(),
+                senses: hash_set![sense],
+            };
+
+            if let Some(entry) = self.entries.get_mut(term.as_str()) {
+                entry.etymologies.push(ety);
+            } else {
+                let entry = Entry {
+                    etymologies: vec![ety],
+                    term: term.to_owned(),
+                    rank: None,
+                    media: vec![],
+                    see_also: see_also.map(EntryRef::from),
+                };
+
+                self.entries.insert(term.clone(), entry);
+            }
+        }
+    }
+
+    /// Returns the usage examples of `sense`, and its quotations as notes
+    /// whose value is where the quotation is from. Quotations are dropped if
+    /// the options say so, and at most `max_examples` of the rest are kept.
+    fn examples(&self, sense: &WordSense) -> (Vec<Example>, Vec<Note>) {
+        let mut examples = vec![];
+        let mut quotations = vec![];
+
+        let kept = sense
+            .examples
+            .iter()
+            .filter(|example| example.text.as_deref().is_some_and(|text| !text.is_empty()))
+            .filter(|example| !(self.skip_quotations && example.is_quotation()))
+            .take(self.max_examples.unwrap_or(usize::MAX));
+
+        for example in kept {
+            let converted = Example {
+                value: example.text.clone().unwrap_or_default(),
+                translations: example
+                    .english
+                    .iter()
+                    .map(|english| Translation {
+                        lang: self.edition.gloss_language().to_string(),
+                        value: english.clone(),
+                    })
+                    .collect(),
+                pronunciations: example
+                    .roman
+                    .iter()
+                    .map(|roman| Pronunciation {
+                        kind: Some(PronunciationKind::Other("romanization".to_string())),
+                        value: roman.clone(),
+                        media: vec![],
+                    })
+                    .collect(),
+            };
+
+            if example.is_quotation() {
+                quotations.push(Note {
+                    id: None,
+                    value: example
+                        .reference
+                        .clone()
+                        .unwrap_or_else(|| "Quotation".to_string()),
+                    examples: vec![converted],
+                });
+            } else {
+                examples.push(converted);
+            }
+        }
+
+        (examples, quotations)
+    }
+
+    /// Returns a note linking to every lemma `sense` is a form of, e.g.
+    /// `inflection:leave` or `alternative:color`, with Wiktionary's extra
+    /// text about the form (if any) as its example.
+    fn lemma_links(&self, sense: &WordSense) -> Vec<Note> {
+        let mut links: Vec<Note> = vec![];
+
+        for (kind, link) in sense.lemmas() {
+            let note = Note {
+                id: None,
+                value: format!("{}:{}", kind, self.normalizer.apply(&link.word)),
+                examples: link
+                    .extra
+                    .iter()
+                    .map(|extra| Example {
+                        value: extra.clone(),
+                        translations: vec![],
+                        pronunciations: vec![],
+                    })
+                    .collect(),
+            };
+
+            if !links.contains(&note) {
+                links.push(note);
+            }
+        }
+
+        links
+    }
+
+    /// Returns cross-references to the words in `relations`, e.g.
+    /// `synonym:large`. Readers render them as links to the word: relations
+    /// of a single sense are notes of its definition, relations of a whole
+    /// entry are tags of its sense.
+    fn cross_references<'a>(
+        &self,
+        relations: impl Iterator<Item = (&'static str, &'a WordLink)>,
+    ) -> Vec<String> {
+        let mut references: Vec<String> = vec![];
+
+        for (relation, link) in relations {
+            let reference = format!("{}:{}", relation, self.normalizer.apply(&link.word));
+
+            if !references.contains(&reference) {
+                references.push(reference);
+            }
+        }
+
+        references
+    }
+
+    /// Returns the translations of `entry`, sense by sense. Translations of
+    /// the whole entry follow the sense whose gloss their `sense` names, or
+    /// come last if they name none.
+    fn translations(&mut self, entry: &WiktionaryEntry) -> Vec<Translation> {
+        let mut unmatched: Vec<&schema::Translation> = entry.translations.iter().collect();
+        let mut ordered: Vec<&schema::Translation> = vec![];
+
+        for sense in &entry.senses {
+            ordered.extend(&sense.translations);
+
+            let (matched, rest): (Vec<_>, Vec<_>) = unmatched.into_iter().partition(|t| {
+                t.sense
+                    .as_deref()
+                    .is_some_and(|name| sense.glosses.iter().any(|gloss| same_gloss(name, gloss)))
+            });
+
+            ordered.extend(matched);
+            unmatched = rest;
+        }
+
+        ordered.extend(unmatched);
+
+        let mut seen = HashSet::new();
+        let mut translations = vec![];
+
+        for translation in ordered {
+            let Some(word) = translation.word.as_deref().filter(|w| !w.is_empty()) else {
+                continue;
+            };
+
+            let Some(lang) = self.translation_language(translation) else {
+                continue;
+            };
+
+            if !self.translation_languages.is_empty() && !self.translation_languages.contains(&lang)
+            {
+                continue;
+            }
+
+            // odict translations are only a language and the word, so the
+            // romanization and qualifiers (e.g. "feminine") are left out
+            // rather than mixed into the word
+            let value = word.to_string();
+
+            if seen.insert((lang.clone(), value.clone())) {
+                translations.push(Translation { lang, value });
+            }
+        }
+
+        translations
+    }
+
+    /// The ISO 639-3 code of the language of `translation`, by its
+    /// Wiktionary code or else its name.
+    pub(super) fn translation_language(
+        &mut self,
+        translation: &schema::Translation,
+    ) -> Option<String> {
+        let key = translation.code.as_ref().unwrap_or(&translation.lang);
+
+        if let Some(code) = self.language_codes.get(key) {
+            return code.clone();
+        }
+
+        let code = translation
+            .code
+            .as_deref()
+            .and_then(|code| self.languages.code(code))
+            .or_else(|| self.languages.code_for_kaikki(&translation.lang));
+
+        self.language_codes.insert(key.clone(), code.clone());
+
+        code
+    }
+
+    /// Reports the part-of-speech values that had no mapping, most common
+    /// first.
+    pub(super) fn report_missing_pos(&self, reporter: &dyn Reporter) {
+        if self.missing_pos.is_empty() {
+            return;
+        }
+
+        let mut missing: Vec<_> = self.missing_pos.iter().collect();
+
+        missing.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+
+        reporter.warn(&format!(
+            "{} entries have parts of speech with no mapping, kept as-is: {}",
+            missing.iter().map(|(_, count)| *count).sum::<usize>(),
+            missing
+                .iter()
+                .map(|(pos, count)| format!("{} ({})", pos, count))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+    }
+}
+
+/// Whether the `sense` of a translation table names `gloss`, ignoring case
+/// and trailing punctuation.
+pub(super) fn same_gloss(sense: &str, gloss: &str) -> bool {
+    let trim = |s: &str| {
+        s.trim()
+            .trim_end_matches(['.', ';', ':'])
+            .trim_end()
+            .to_lowercase()
+    };
+
+    trim(sense) == trim(gloss)
 }
 
-impl Into<Option<Pronunciation>> for &Sound {
-    fn into(self) -> Option<Pronunciation> {
+impl From<&Sound> for Option<Pronunciation> {
+    fn from(sound: &Sound) -> Self {
         // Only support Pinyin and IPA right now
-        if self.ipa.is_none() && self.zh_pron.is_none() {
+        if sound.ipa.is_none() && sound.zh_pron.is_none() {
             return None;
         }
 
-        let media = vec![&self.mp3_url, &self.ogg_url]
+        let media = [&sound.mp3_url, &sound.ogg_url]
             .into_iter()
             .filter_map(|u| u.to_owned())
             .map(|url| MediaURL {
@@ -61,22 +584,22 @@ impl Into<Option<Pronunciation>> for &Sound {
             })
             .collect::<Vec<MediaURL>>();
 
-        if let Some(ipa) = &self.ipa {
+        if let Some(ipa) = &sound.ipa {
             return Pronunciation {
                 kind: Some(PronunciationKind::IPA),
                 value: ipa.to_owned(),
                 media,
             }
             .into();
-        } else if let Some(zh_pron) = &self.zh_pron {
-            if self.tags.contains(&"Pinyin".to_string()) {
-                return Pronunciation {
-                    kind: Some(PronunciationKind::Pinyin),
-                    value: zh_pron.to_owned(),
-                    media,
-                }
-                .into();
+        } else if let Some(zh_pron) = &sound.zh_pron
+            && sound.tags.contains(&"Pinyin".to_string())
+        {
+            return Pronunciation {
+                kind: Some(PronunciationKind::Pinyin),
+                value: zh_pron.to_owned(),
+                media,
             }
+            .into();
         }
 
         None
@@ -88,167 +611,527 @@ impl Converter for WiktionaryConverter {
 
     fn convert(
         &mut self,
-        term: &Term,
+        reporter: &dyn Reporter,
         frequency_map: &Option<FrequencyMap>,
-        data: &Vec<WiktionaryEntry>,
+        data: &[WiktionaryEntry],
         language: Option<String>,
     ) -> anyhow::Result<Dictionary> {
-        term.write_line("🔄 Converting the dictionary...")?;
+        reporter.step("Converting the dictionary");
 
-        self.missing_pos = vec![];
+        self.start(language.as_deref())?;
 
-        let progress = indicatif::ProgressBar::new(data.len() as u64);
+        let progress = reporter.progress(ProgressKind::Items(data.len() as u64));
 
-        progress.set_style(STYLE_PROGRESS.clone());
+        for entry in data {
+            self.add(entry);
 
-        let mut entries: HashMap<String, Entry> = hash_map! {};
+            progress.set_message(&entry.word);
+            progress.inc(1);
+        }
 
-        for entry in data {
-            let pos = self.resolve_pos(&entry);
-            let term = entry.word.to_owned();
-            let see_also = entry.redirects.as_ref().map(|r| r[0].to_owned());
-            let etymology_text = entry.etymology_text.to_owned();
-            let pronunciations = entry
-                .sounds
-                .iter()
-                .map(|s| s.to_owned())
-                .filter_map(|s| s.into())
-                .collect::<Vec<odict::Pronunciation>>();
+        drop(progress);
 
-            let mut definitions: Vec<DefinitionType> = vec![];
-            let mut group_map: HashMap<String, usize> = hash_map! {};
+        reporter.done("Conversion complete");
 
-            let mut lemma: Option<EntryRef> = None;
-            let mut tags: Vec<String> = vec![];
+        self.finish(reporter, frequency_map)
+    }
 
-            for sense in &entry.senses {
-                tags.extend(sense.tags.iter().cloned());
+    fn new() -> anyhow::Result<Self>
+    where
+        Self: Sized,
+    {
+        Self::with_options(&WiktionaryOptions::default(), Languages::builtin()?)
+    }
+}
 
-                if sense.form_of.len() > 0 {
-                    if let Some(fo) = &sense.form_of.get(0) {
-                        lemma = Some(EntryRef::from(fo.word.to_owned()));
-                    }
-                }
+/// Drops the cross-references of `dictionary` to words that aren't in it,
+/// for when entries are left out after it is built, e.g. by a manifest's
+/// filters. [`WiktionaryConverter::finish`] already drops the ones to words
+/// it has no entry for.
+pub fn prune_cross_references(mut dictionary: Dictionary) -> Dictionary {
+    let terms: HashSet<String> = dictionary.entries.iter().map(|e| e.term.clone()).collect();
 
-                // Glosses with 2 senses typically have subdefinitions
-                if sense.glosses.len() == 2 {
-                    let parent = sense.glosses[0].to_owned();
-                    let child = sense.glosses[1].to_owned();
-                    let definition = Definition {
-                        id: None,
-                        value: child.to_owned(),
-                        examples: vec![],
-                        notes: vec![],
-                    };
-
-                    if let Some(&idx) = group_map.get(&parent) {
-                        if let DefinitionType::Group(group) = &mut definitions[idx] {
-                            group.definitions.push(definition);
+    dictionary.entries = dictionary
+        .entries
+        .into_iter()
+        .map(|mut entry| {
+            prune_links(&mut entry, &terms);
+            entry
+        })
+        .collect();
+
+    dictionary
+}
+
+/// Drops the cross-references and lemma links of `entry` that can't be
+/// followed, those to words that aren't in `terms` or to the word itself.
+fn prune_links(entry: &mut Entry, terms: &HashSet<String>) {
+    let followed = |word: &str| word != entry.term && terms.contains(word);
+    let linked = |value: &str| match value.split_once(':') {
+        Some((kind, word)) if RELATIONS.contains(&kind) || LEMMA_KINDS.contains(&kind) => {
+            followed(word)
+        }
+        _ => true,
+    };
+
+    for ety in &mut entry.etymologies {
+        ety.senses = std::mem::take(&mut ety.senses)
+            .into_iter()
+            .map(|mut sense| {
+                sense.lemma = sense.lemma.filter(|lemma| followed(lemma.as_ref()));
+                sense.tags.retain(|tag| linked(tag));
+
+                for definition in &mut sense.definitions {
+                    match definition {
+                        DefinitionType::Definition(definition) => {
+                            definition.notes.retain(|note| linked(&note.value));
+                        }
+                        DefinitionType::Group(group) => {
+                            for definition in &mut group.definitions {
+                                definition.notes.retain(|note| linked(&note.value));
+                            }
                         }
-                    } else {
-                        let group = DefinitionType::Group(Group {
-                            id: None,
-                            description: parent.to_owned(),
-                            definitions: vec![Definition {
-                                id: None,
-                                value: child.to_owned(),
-                                examples: vec![],
-                                notes: vec![],
-                            }],
-                        });
-                        definitions.push(group);
-                        group_map.insert(parent, definitions.len() - 1);
                     }
-                } else if let Some(gloss) = sense.glosses.get(0) {
-                    let definition = Definition {
-                        id: None,
-                        value: gloss.to_owned(),
-                        examples: vec![],
-                        notes: vec![],
-                    };
-                    definitions.push(DefinitionType::Definition(definition));
                 }
+
+                sense
+            })
+            .collect();
+    }
+}
+
+/// Adds what `sense` says to `existing`, a sense of the same part of speech:
+/// its definitions, translations, forms and tags, and its lemma if
+/// `existing` has none.
+fn merge_senses(mut existing: Sense, sense: Sense) -> Sense {
+    fn extend<T: PartialEq>(existing: &mut Vec<T>, new: Vec<T>) {
+        for item in new {
+            if !existing.contains(&item) {
+                existing.push(item);
             }
+        }
+    }
 
-            let etymology_number = entry.etymology_number.unwrap_or(1);
+    existing.definitions.extend(sense.definitions);
+    existing.lemma = existing.lemma.or(sense.lemma);
 
-            let forms = entry
-                .forms
-                .iter()
-                .map(|f| Form {
-                    kind: None,
-                    term: EntryRef::from(f.form.to_owned()),
-                    tags: f.tags.to_owned(),
-                })
-                .collect();
-
-            let sense = Sense {
-                pos: pos.to_owned(),
-                lemma: lemma.to_owned(),
-                tags,
-                translations: vec![],
-                forms,
-                definitions: definitions.to_owned(),
-            };
+    extend(&mut existing.translations, sense.translations);
+    extend(&mut existing.forms, sense.forms);
+    extend(&mut existing.tags, sense.tags);
 
-            if let Some(ety) = entries
-                .get_mut(term.as_str())
-                .and_then(|e| e.etymologies.get_mut(etymology_number as usize - 1))
-            {
-                if let Some(sense) = ety.senses.get(&pos) {
-                    let mut new_sense = sense.clone();
-                    new_sense.definitions.append(&mut definitions);
-                    ety.senses.replace(new_sense);
-                } else {
-                    ety.senses.insert(sense);
+    existing
+}
+
+/// Returns an entry for every form of a headword that isn't a headword
+/// itself, so that "mice" can be looked up as well as "mouse". A redirect
+/// has no definitions, only a sense per part of speech whose lemma is the
+/// headword it is a form of, and the first of those headwords as its
+/// `see_also`.
+fn form_redirects(entries: &HashMap<String, Entry>) -> HashMap<String, Entry> {
+    let mut redirects: HashMap<String, Entry> = HashMap::new();
+
+    // Headwords are visited in order so the first lemma of a form doesn't
+    // depend on the order of the map
+    let mut lemmas: Vec<&Entry> = entries.values().collect();
+
+    lemmas.sort_by(|a, b| a.term.cmp(&b.term));
+
+    for lemma in lemmas {
+        for sense in lemma.etymologies.iter().flat_map(|ety| &ety.senses) {
+            for form in &sense.forms {
+                let term = form.term.as_ref();
+
+                if term == lemma.term || entries.contains_key(term) {
+                    continue;
                 }
-            } else {
-                let ety = Etymology {
-                    id: None,
-                    pronunciations,
-                    description: etymology_text.to_owned(),
-                    senses: hash_set![sense],
-                };
 
-                if let Some(entry) = entries.get_mut(term.as_str()) {
-                    entry.etymologies.push(ety);
-                } else {
-                    let rank = frequency_map.as_ref().and_then(|m| m.get_frequency(&term));
+                let redirect = redirects.entry(term.to_string()).or_insert_with(|| Entry {
+                    term: term.to_string(),
+                    rank: None,
+                    see_also: Some(EntryRef::from(lemma.term.as_str())),
+                    media: vec![],
+                    etymologies: vec![Etymology {
+                        id: None,
+                        pronunciations: vec![],
+                        description: None,
+                        senses: hash_set![],
+                    }],
+                });
 
-                    let entry = Entry {
-                        etymologies: vec![ety],
-                        term: term.to_owned(),
-                        rank,
-                        media: vec![],
-                        see_also: see_also.map(|s| EntryRef::from(s)),
-                    };
+                let senses = &mut redirect.etymologies[0].senses;
 
-                    entries.insert(term.clone(), entry);
+                if !senses.iter().any(|s| s.pos == sense.pos) {
+                    senses.insert(Sense {
+                        pos: sense.pos.clone(),
+                        lemma: Some(EntryRef::from(lemma.term.as_str())),
+                        tags: form.tags.clone(),
+                        translations: vec![],
+                        forms: vec![],
+                        definitions: vec![],
+                    });
                 }
             }
+        }
+    }
 
-            progress.set_message(term);
-            progress.inc(1);
+    redirects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Silent;
+
+    /// Converts the records on `lines` into an English dictionary.
+    fn convert(lines: &[&str]) -> Dictionary {
+        convert_with(&WiktionaryOptions::default(), lines)
+    }
+
+    fn convert_with(options: &WiktionaryOptions, lines: &[&str]) -> Dictionary {
+        let entries: Vec<WiktionaryEntry> = lines
+            .iter()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        WiktionaryConverter::with_options(options, Languages::builtin().unwrap())
+            .unwrap()
+            .convert(&Silent, &None, &entries, Some("eng".to_string()))
+            .unwrap()
+    }
+
+    /// The only sense of the entry for `term`.
+    fn sense<'a>(dictionary: &'a Dictionary, term: &str) -> &'a Sense {
+        let entry = dictionary
+            .entries
+            .iter()
+            .find(|entry| entry.term == term)
+            .unwrap();
+
+        entry.etymologies[0].senses.iter().next().unwrap()
+    }
+
+    /// The definitions of the only sense of the entry for `term`.
+    fn definitions<'a>(dictionary: &'a Dictionary, term: &str) -> Vec<&'a Definition> {
+        sense(dictionary, term)
+            .definitions
+            .iter()
+            .flat_map(|definition| match definition {
+                DefinitionType::Definition(definition) => vec![definition],
+                DefinitionType::Group(group) => group.definitions.iter().collect(),
+            })
+            .collect()
+    }
+
+    const EXAMPLES: &str = r#"{"word": "dog", "lang": "English", "lang_code": "en", "pos": "noun",
+        "senses": [{"glosses": ["A mammal"], "examples": [
+            {"text": "The dog barked.", "type": "example"},
+            {"text": "Hark, the dog!", "type": "quotation", "ref": "1897, Some Novel"},
+            {"text": "A dog's life.", "ref": "1600, Another Book"},
+            {"text": "Собака лает.", "english": "The dog barks.", "roman": "Sobaka layet."},
+            {"text": ""}
+        ]}]}"#;
+
+    fn example(value: &str) -> Example {
+        Example {
+            value: value.to_string(),
+            translations: vec![],
+            pronunciations: vec![],
         }
+    }
 
-        progress.finish_and_clear();
+    #[test]
+    fn keeps_examples_and_quotations() {
+        let dictionary = convert(&[EXAMPLES]);
+        let definition = definitions(&dictionary, "dog")[0];
 
-        term.clear_last_lines(1)?;
-        term.write_line("✅ Conversion complete")?;
+        assert_eq!(
+            definition.examples,
+            [
+                example("The dog barked."),
+                Example {
+                    translations: vec![Translation {
+                        lang: "eng".to_string(),
+                        value: "The dog barks.".to_string(),
+                    }],
+                    pronunciations: vec![Pronunciation {
+                        kind: Some(PronunciationKind::Other("romanization".to_string())),
+                        value: "Sobaka layet.".to_string(),
+                        media: vec![],
+                    }],
+                    ..example("Собака лает.")
+                },
+            ]
+        );
 
-        Ok(Dictionary {
-            id: ID::new(),
-            entries: entries.values().cloned().collect(),
-            name: language.map(|lang| format!("{} Wiktionary", SUPPORTED_LANGUAGES[lang.as_str()])),
-        })
+        // Quotations are notes saying where they are from
+        assert_eq!(
+            definition.notes,
+            [
+                Note {
+                    id: None,
+                    value: "1897, Some Novel".to_string(),
+                    examples: vec![example("Hark, the dog!")],
+                },
+                Note {
+                    id: None,
+                    value: "1600, Another Book".to_string(),
+                    examples: vec![example("A dog's life.")],
+                },
+            ]
+        );
     }
 
-    fn new() -> anyhow::Result<Self>
-    where
-        Self: Sized,
-    {
-        Ok(Self {
-            missing_pos: vec![],
-        })
+    #[test]
+    fn caps_examples_and_skips_quotations() {
+        let capped = convert_with(
+            &WiktionaryOptions {
+                max_examples: Some(2),
+                ..Default::default()
+            },
+            &[EXAMPLES],
+        );
+        let definition = definitions(&capped, "dog")[0];
+
+        assert_eq!(definition.examples, [example("The dog barked.")]);
+        assert_eq!(definition.notes.len(), 1);
+
+        let unquoted = convert_with(
+            &WiktionaryOptions {
+                skip_quotations: true,
+                max_examples: Some(2),
+                ..Default::default()
+            },
+            &[EXAMPLES],
+        );
+        let definition = definitions(&unquoted, "dog")[0];
+
+        assert_eq!(definition.examples.len(), 2);
+        assert!(definition.notes.is_empty());
+    }
+
+    #[test]
+    fn links_lemmas_of_senses_without_glosses() {
+        let dictionary = convert(&[
+            r#"{"word": "left", "lang": "English", "lang_code": "en", "pos": "verb",
+                "senses": [{"form_of": [{"word": "leave"}]}]}"#,
+            r#"{"word": "colour", "lang": "English", "lang_code": "en", "pos": "noun",
+                "senses": [{"alt_of": [{"word": "color"}]}]}"#,
+            r#"{"word": "leave", "lang": "English", "lang_code": "en", "pos": "verb",
+                "senses": [{"glosses": ["To go away"]}]}"#,
+            r#"{"word": "color", "lang": "English", "lang_code": "en", "pos": "noun",
+                "senses": [{"glosses": ["A hue"]}]}"#,
+        ]);
+
+        let left = sense(&dictionary, "left");
+
+        assert!(left.definitions.is_empty());
+        assert_eq!(left.lemma, Some(EntryRef::from("leave")));
+        assert_eq!(left.tags, ["inflection:leave"]);
+        assert_eq!(sense(&dictionary, "colour").tags, ["alternative:color"]);
+    }
+
+    #[test]
+    fn prunes_lemma_links_to_missing_words() {
+        let dictionary = convert(&[
+            r#"{"word": "left", "lang": "English", "lang_code": "en", "pos": "verb",
+                "senses": [{"form_of": [{"word": "leave"}]}]}"#,
+            r#"{"word": "gone", "lang": "English", "lang_code": "en", "pos": "verb",
+                "senses": [{"form_of": [{"word": "go"}]}]}"#,
+            r#"{"word": "colour", "lang": "English", "lang_code": "en", "pos": "noun",
+                "senses": [
+                    {"glosses": ["A hue"], "alt_of": [{"word": "color"}]},
+                    {"glosses": ["A flag"], "alt_of": [{"word": "flag"}]}
+                ]}"#,
+            r#"{"word": "leave", "lang": "English", "lang_code": "en", "pos": "verb",
+                "senses": [{"glosses": ["To go away"]}]}"#,
+            r#"{"word": "color", "lang": "English", "lang_code": "en", "pos": "noun",
+                "senses": [{"glosses": ["A hue"]}]}"#,
+        ]);
+
+        let left = sense(&dictionary, "left");
+        let gone = sense(&dictionary, "gone");
+
+        assert_eq!(left.lemma, Some(EntryRef::from("leave")));
+        assert_eq!(left.tags, ["inflection:leave"]);
+        assert_eq!(gone.lemma, None);
+        assert!(gone.tags.is_empty());
+
+        let notes: Vec<Vec<&str>> = sense(&dictionary, "colour")
+            .definitions
+            .iter()
+            .map(|definition| match definition {
+                DefinitionType::Definition(definition) => definition
+                    .notes
+                    .iter()
+                    .map(|note| note.value.as_str())
+                    .collect(),
+                DefinitionType::Group(_) => unreachable!(),
+            })
+            .collect();
+
+        assert_eq!(notes, [vec!["alternative:color"], vec![]]);
+    }
+
+    #[test]
+    fn translates_into_the_bare_word() {
+        let dictionary = convert(&[r#"{"word": "dog", "lang": "English", "lang_code": "en",
+            "pos": "noun", "senses": [{"glosses": ["A mammal"]}],
+            "translations": [
+                {"lang": "Russian", "code": "ru", "word": "собака", "roman": "sobaka",
+                 "tags": ["feminine"]},
+                {"lang": "Russian", "code": "ru", "word": "собака", "roman": "sobaka",
+                 "tags": ["feminine", "animate"]},
+                {"lang": "French", "code": "fr", "word": "chien", "tags": ["masculine"]}
+            ]}"#]);
+
+        assert_eq!(
+            sense(&dictionary, "dog").translations,
+            [
+                Translation {
+                    lang: "rus".to_string(),
+                    value: "собака".to_string(),
+                },
+                Translation {
+                    lang: "fra".to_string(),
+                    value: "chien".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn merges_records_sharing_a_sense() {
+        let dictionary = convert(&[
+            r#"{"word": "dog", "lang": "English", "lang_code": "en", "pos": "noun",
+                "sounds": [{"ipa": "/dɒɡ/"}],
+                "senses": [{"glosses": ["A mammal"], "tags": ["countable"],
+                    "translations": [{"lang": "French", "code": "fr", "word": "chien"}]}],
+                "forms": [{"form": "dogs", "tags": ["plural"]}]}"#,
+            r#"{"word": "dog", "lang": "English", "lang_code": "en", "pos": "noun",
+                "sounds": [{"ipa": "/dɒɡ/"}, {"ipa": "/dɔɡ/"}],
+                "etymology_text": "From Old English docga.",
+                "senses": [{"glosses": ["A man"], "tags": ["countable", "informal"],
+                    "translations": [{"lang": "French", "code": "fr", "word": "chien"},
+                                     {"lang": "German", "code": "de", "word": "Hund"}]}],
+                "forms": [{"form": "dogs", "tags": ["plural"]}]}"#,
+        ]);
+
+        let entry = dictionary.entries.iter().next().unwrap();
+        let ety = &entry.etymologies[0];
+        let sense = sense(&dictionary, "dog");
+
+        assert_eq!(entry.etymologies.len(), 1);
+        assert_eq!(ety.senses.len(), 1);
+        assert_eq!(
+            ety.pronunciations
+                .iter()
+                .map(|p| p.value.as_str())
+                .collect::<Vec<_>>(),
+            ["/dɒɡ/", "/dɔɡ/"]
+        );
+        assert_eq!(ety.description.as_deref(), Some("From Old English docga."));
+        assert_eq!(
+            definitions(&dictionary, "dog")
+                .iter()
+                .map(|d| d.value.as_str())
+                .collect::<Vec<_>>(),
+            ["A mammal", "A man"]
+        );
+        assert_eq!(
+            sense
+                .translations
+                .iter()
+                .map(|t| t.value.as_str())
+                .collect::<Vec<_>>(),
+            ["chien", "Hund"]
+        );
+        assert_eq!(sense.forms.len(), 1);
+        assert_eq!(sense.tags, ["countable", "informal"]);
+    }
+
+    #[test]
+    fn links_relations_to_headwords() {
+        let dictionary = convert(&[
+            r#"{"word": "large", "lang": "English", "lang_code": "en", "pos": "adj",
+                "senses": [{"glosses": ["Big"],
+                    "synonyms": [{"word": "big"}, {"word": "huge"}, {"word": "big"}],
+                    "antonyms": [{"word": "small"}]}],
+                "derived": [{"word": "largely"}, {"word": "largeness"}, {"word": "large"}]}"#,
+            r#"{"word": "big", "lang": "English", "lang_code": "en", "pos": "adj",
+                "senses": [{"glosses": ["Large"]}]}"#,
+            r#"{"word": "largely", "lang": "English", "lang_code": "en", "pos": "adv",
+                "senses": [{"glosses": ["Mostly"]}]}"#,
+        ]);
+
+        // Relations of a sense are notes of its definition, relations of the
+        // whole entry are tags, and only those to other headwords are kept
+        assert_eq!(
+            definitions(&dictionary, "large")[0]
+                .notes
+                .iter()
+                .map(|note| note.value.as_str())
+                .collect::<Vec<_>>(),
+            ["synonym:big"]
+        );
+        assert_eq!(sense(&dictionary, "large").tags, ["derived:largely"]);
+
+        // Links to entries filtered out later are dropped too
+        let mut filtered = dictionary;
+
+        filtered.entries.retain(|entry| entry.term != "big");
+
+        let pruned = prune_cross_references(filtered);
+
+        assert!(definitions(&pruned, "large")[0].notes.is_empty());
+        assert_eq!(sense(&pruned, "large").tags, ["derived:largely"]);
+    }
+
+    const MICE: [&str; 2] = [
+        r#"{"word": "mouse", "lang": "English", "lang_code": "en", "pos": "noun",
+            "senses": [{"glosses": ["A rodent"]}],
+            "forms": [{"form": "mice", "tags": ["plural"]},
+                      {"form": "mouses", "tags": ["plural"]},
+                      {"form": "mouse", "tags": ["canonical"]}]}"#,
+        r#"{"word": "mouses", "lang": "English", "lang_code": "en", "pos": "verb",
+            "senses": [{"glosses": ["Hunts mice"]}]}"#,
+    ];
+
+    #[test]
+    fn redirects_forms_that_are_not_headwords() {
+        assert_eq!(convert(&MICE).entries.len(), 2);
+
+        let dictionary = convert_with(
+            &WiktionaryOptions {
+                form_redirects: true,
+                ..Default::default()
+            },
+            &MICE,
+        );
+
+        let mut terms: Vec<&str> = dictionary.entries.iter().map(|e| e.term.as_str()).collect();
+
+        terms.sort();
+
+        assert_eq!(terms, ["mice", "mouse", "mouses"]);
+
+        let mice = dictionary
+            .entries
+            .iter()
+            .find(|e| e.term == "mice")
+            .unwrap();
+        let redirect = sense(&dictionary, "mice");
+
+        assert_eq!(mice.see_also, Some(EntryRef::from("mouse")));
+        assert_eq!(redirect.pos, PartOfSpeech::N);
+        assert_eq!(redirect.lemma, Some(EntryRef::from("mouse")));
+        assert_eq!(redirect.tags, ["plural"]);
+        assert!(redirect.definitions.is_empty());
+
+        // A form that is a headword of its own is left as it is
+        let mouses = sense(&dictionary, "mouses");
+
+        assert_eq!(mouses.pos, PartOfSpeech::V);
+        assert_eq!(mouses.lemma, None);
+        assert_eq!(definitions(&dictionary, "mouses")[0].value, "Hunts mice");
     }
 }
@@ -1,36 +1,48 @@
 use crate::processors::traits::Downloader;
 
-use super::consts::SUPPORTED_LANGUAGES;
+use super::{Edition, LanguageProfile, Languages};
 
 pub struct WiktionaryDownloader {
-    pub language: String,
+    profile: LanguageProfile,
+    edition: Edition,
+}
+
+impl WiktionaryDownloader {
+    pub fn with_profile(profile: LanguageProfile, edition: Edition) -> Self {
+        Self { profile, edition }
+    }
 }
 
 impl Downloader for WiktionaryDownloader {
     fn url(&self) -> String {
-        let languages = SUPPORTED_LANGUAGES;
-        let language = languages.get(self.language.as_str()).unwrap();
+        // The directory is named after the language, the file after the
+        // language without spaces or punctuation, e.g. "Old English" and
+        // "kaikki.org-dictionary-OldEnglish.jsonl"
+        let name = self.profile.kaikki_name(self.edition);
+        let file_name: String = name.chars().filter(|c| c.is_alphanumeric()).collect();
 
         format!(
-            "https://kaikki.org/dictionary/{}/kaikki.org-dictionary-{}.jsonl",
-            language, language
+            "{}/{}/kaikki.org-dictionary-{}.jsonl",
+            self.edition.base_url(),
+            name.replace(' ', "%20"),
+            file_name
         )
     }
 
+    fn source(&self) -> String {
+        self.edition.source(&self.profile.code)
+    }
+
     fn new(language: &Option<String>) -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        if let Some(lang) = language {
-            if SUPPORTED_LANGUAGES.contains_key(lang.as_str()) {
-                Ok(Self {
-                    language: lang.clone(),
-                })
-            } else {
-                anyhow::bail!("Unsupported language: {}", lang);
-            }
-        } else {
-            anyhow::bail!("A language is required for the Wiktionary downloader");
+        match language {
+            Some(lang) => Ok(Self::with_profile(
+                Languages::builtin()?.profile(lang)?,
+                Edition::default(),
+            )),
+            None => anyhow::bail!("A language is required for the Wiktionary downloader"),
         }
     }
 }
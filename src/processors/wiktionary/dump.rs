@@ -0,0 +1,345 @@
+//! Dictionaries of many languages built from kaikki's dump of every language
+//! at once, which is downloaded and split by language only once however
+//! many languages are built.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::OpenOptions,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+use odict::Dictionary;
+use rayon::prelude::*;
+use serde::Deserialize;
+use tempdir::TempDir;
+
+use crate::{
+    cache::Cache,
+    error::Error,
+    frequency::FrequencyMap,
+    processors::Processor,
+    report::{ProgressKind, Reporter},
+    utils::{open_input, raw_lines},
+};
+
+use super::{
+    WiktionaryConverter, WiktionaryProcessor,
+    extractor::{Rejected, WiktionaryExtractor},
+};
+
+/// How many lines are parsed in parallel at a time.
+const BATCH_SIZE: usize = 10_000;
+
+/// Just enough of a record to tell which language it belongs to. Records
+/// without a language are pages that aren't entries, such as redirects.
+#[derive(Deserialize)]
+struct Route {
+    lang_code: Option<String>,
+}
+
+/// The lines of one language, split off the dump into a file of their own.
+struct Split {
+    path: PathBuf,
+    entries: usize,
+}
+
+/// The dump split by language.
+struct Dump {
+    splits: BTreeMap<String, Split>,
+    /// The lines that were rejected because they aren't records at all
+    rejected: Vec<Rejected>,
+    /// How many lines are parsed in all, counting the rejected ones
+    total: usize,
+}
+
+impl WiktionaryProcessor {
+    /// Builds the dictionaries of `languages` (ISO 639-3 codes, or every
+    /// language in the dump if empty) from the all-languages dump, reading
+    /// it from `input` instead of downloading it if given.
+    ///
+    /// The dump is read once, and the lines of each language are set aside
+    /// in a temporary file next to the cache. The dictionaries are then
+    /// built from those files one after another and handed to `save` as
+    /// soon as they are built, so only one is held in memory at a time.
+    /// The temporary files take as much disk space as the uncompressed
+    /// lines of the languages built, which for every language is the size
+    /// of the whole dump.
+    ///
+    /// Each line is parsed in full only once, when its language is built,
+    /// so a line that doesn't parse fails the build (or, in tolerant mode,
+    /// counts towards the error rate) only when its language is reached.
+    pub async fn process_dump(
+        &self,
+        reporter: &dyn Reporter,
+        cache: &Cache,
+        languages: &[String],
+        input: Option<PathBuf>,
+        mut save: impl FnMut(&str, &Dictionary) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        // kaikki.org publishes the wiktextract data of every language of an
+        // edition next to its per-language extracts
+        let edition = self.options.edition;
+        let url = format!("{}/raw-wiktextract-data.jsonl.gz", edition.base_url());
+        let description = "dictionary";
+        let source = edition.source("all");
+        let path = match input {
+            Some(input) => cache.use_local(&input, &url, description, &source, reporter)?,
+            None => cache.fetch(&url, description, &source, reporter).await?,
+        };
+
+        if languages.is_empty() {
+            reporter.warn(
+                "Building every language in the dump, which needs as much free disk space as the uncompressed dump",
+            );
+        }
+
+        std::fs::create_dir_all(&cache.root)?;
+
+        let spill = TempDir::new_in(&cache.root, "dump")?;
+        let extractor: WiktionaryExtractor = self.extractor()?;
+        let Dump {
+            splits,
+            mut rejected,
+            total,
+        } = self
+            .split_dump(reporter, &extractor, &path, languages, spill.path())
+            .with_context(|| format!("Failed to extract {}", path.display()))?;
+
+        for language in languages {
+            if !splits.contains_key(language) {
+                reporter.warn(&format!("The dump has no entries for {}", language));
+            }
+        }
+
+        for (language, split) in splits {
+            reporter.step(&format!("Building the {} dictionary", language));
+
+            let mut converter = self
+                .convert_split(reporter, &extractor, &language, &split, &mut rejected)
+                .with_context(|| format!("Failed to extract {}", path.display()))?;
+
+            std::fs::remove_file(&split.path)?;
+
+            // The rate is checked against every line the dump sets aside,
+            // so once it is exceeded it stays exceeded and the build stops
+            // before the dictionary is written
+            if extractor.exceeds_error_rate(rejected.len(), total) {
+                extractor.finish(reporter, &rejected, total)?;
+            }
+
+            let frequency_map = FrequencyMap::from_source(
+                self.frequency_source(&language),
+                &language,
+                reporter,
+                cache,
+            )
+            .await?;
+            let dictionary = converter.finish(reporter, &frequency_map)?;
+
+            save(&language, &dictionary)?;
+        }
+
+        extractor.finish(reporter, &rejected, total)
+    }
+
+    /// Reads the dump at `path` once, writing the lines of each language to
+    /// a file of their own in `dir`.
+    ///
+    /// Only the language of each line is parsed here, the entries are
+    /// parsed once when their language is converted. Lines that aren't
+    /// records at all are rejected, or fail the split, right away.
+    fn split_dump(
+        &self,
+        reporter: &dyn Reporter,
+        extractor: &WiktionaryExtractor,
+        path: &Path,
+        languages: &[String],
+        dir: &Path,
+    ) -> anyhow::Result<Dump> {
+        reporter.step("Splitting the dump by language");
+
+        let progress = reporter.progress(ProgressKind::Lines);
+        let mut lines = raw_lines(open_input(path)?).enumerate();
+        let mut splits: BTreeMap<String, Split> = BTreeMap::new();
+        let mut codes: HashMap<String, Option<String>> = HashMap::new();
+        let mut unknown: HashMap<String, usize> = HashMap::new();
+        let mut rejected = vec![];
+        let mut total = 0;
+
+        loop {
+            let batch = lines
+                .by_ref()
+                .take(BATCH_SIZE)
+                .map(|(i, line)| Ok((i, line?)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            if batch.is_empty() {
+                break;
+            }
+
+            progress.inc(batch.len() as u64);
+
+            let routes = batch
+                .par_iter()
+                .map(|(i, line)| {
+                    match serde_json::from_str::<Route>(&String::from_utf8_lossy(line)) {
+                        Ok(route) => Ok(Ok(route.lang_code)),
+                        // A line without a language can't be a record, so
+                        // parsing it in full only says why
+                        Err(_) => Ok(Err(extractor.parse(*i, line)?.err())),
+                    }
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            // The lines of a batch are appended to their language's file
+            // together, so only as many files as a batch has languages are
+            // open at a time
+            let mut grouped: BTreeMap<String, Vec<(usize, Vec<u8>)>> = BTreeMap::new();
+
+            for ((i, line), route) in batch.into_iter().zip(routes) {
+                let lang_code = match route {
+                    Ok(Some(lang_code)) => lang_code,
+                    Ok(None) => continue,
+                    Err(r) => {
+                        total += 1;
+                        rejected.extend(r);
+                        continue;
+                    }
+                };
+                let code = codes
+                    .entry(lang_code.clone())
+                    .or_insert_with(|| self.languages.code(&lang_code));
+
+                match code {
+                    Some(code) if languages.is_empty() || languages.contains(code) => {
+                        total += 1;
+                        grouped.entry(code.clone()).or_default().push((i, line));
+                    }
+                    None if languages.is_empty() => *unknown.entry(lang_code).or_default() += 1,
+                    _ => {}
+                }
+            }
+
+            for (language, lines) in grouped {
+                let split = splits.entry(language).or_insert_with_key(|language| Split {
+                    path: dir.join(format!("{}.jsonl", language)),
+                    entries: 0,
+                });
+                let mut writer = BufWriter::new(
+                    OpenOptions::new()
+                        .create(true)
+                        .append(true)
+                        .open(&split.path)?,
+                );
+
+                // Each line keeps its number in the dump, for the errors of
+                // the lines that don't parse
+                for (i, line) in &lines {
+                    write!(writer, "{}\t", i)?;
+                    writer.write_all(line)?;
+                    writer.write_all(b"\n")?;
+                }
+
+                writer.flush()?;
+                split.entries += lines.len();
+            }
+        }
+
+        drop(progress);
+
+        reporter.done(&format!(
+            "Split {} entries into {} languages",
+            splits.values().map(|split| split.entries).sum::<usize>(),
+            splits.len()
+        ));
+
+        if !unknown.is_empty() {
+            let mut unknown: Vec<_> = unknown.into_iter().collect();
+
+            unknown.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+            reporter.warn(&format!(
+                "Skipped {} entries of languages without an ISO 639 code: {}",
+                unknown.iter().map(|(_, count)| *count).sum::<usize>(),
+                unknown
+                    .iter()
+                    .map(|(code, count)| format!("{} ({})", code, count))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+
+        Ok(Dump {
+            splits,
+            rejected,
+            total,
+        })
+    }
+
+    /// Parses and converts the lines of `language` split off the dump, in
+    /// the order they were in, adding the lines that don't parse to
+    /// `rejected`.
+    fn convert_split(
+        &self,
+        reporter: &dyn Reporter,
+        extractor: &WiktionaryExtractor,
+        language: &str,
+        split: &Split,
+        rejected: &mut Vec<Rejected>,
+    ) -> anyhow::Result<WiktionaryConverter> {
+        let mut converter = self.converter()?;
+
+        converter
+            .start(Some(language))
+            .map_err(|e| Error::Convert { source: e })?;
+
+        let progress = reporter.progress(ProgressKind::Items(split.entries as u64));
+        let mut lines = raw_lines(open_input(&split.path)?);
+
+        loop {
+            let batch = lines
+                .by_ref()
+                .take(BATCH_SIZE)
+                .collect::<std::io::Result<Vec<_>>>()?;
+
+            if batch.is_empty() {
+                break;
+            }
+
+            let results = batch
+                .par_iter()
+                .map(|line| {
+                    let (index, line) = numbered(line)?;
+
+                    extractor.parse(index, line)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+
+            for result in results {
+                match result {
+                    Ok(entry) => converter.add(&entry),
+                    Err(r) => rejected.push(r),
+                }
+            }
+
+            progress.inc(batch.len() as u64);
+        }
+
+        Ok(converter)
+    }
+}
+
+/// Splits a line of a split file into its number in the dump and the line.
+fn numbered(line: &[u8]) -> anyhow::Result<(usize, &[u8])> {
+    let tab = line
+        .iter()
+        .position(|&b| b == b'\t')
+        .context("A line set aside from the dump has no line number")?;
+
+    Ok((
+        std::str::from_utf8(&line[..tab])?.parse()?,
+        &line[tab + 1..],
+    ))
+}
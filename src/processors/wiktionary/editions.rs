@@ -0,0 +1,346 @@
+//! The Wiktionary editions kaikki.org publishes extracts of. An edition
+//! decides the language of the glosses, so the French edition gives
+//! French-French and English-French dictionaries.
+
+use std::{fmt, str::FromStr};
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+/// A Wiktionary edition, named by the language it is written in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Edition {
+    /// The English Wiktionary
+    #[default]
+    En,
+    /// The French Wiktionary
+    Fr,
+    /// The German Wiktionary
+    De,
+}
+
+impl Edition {
+    /// The ISO 639-1 code of the edition, e.g. `fr`.
+    pub fn code(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Fr => "fr",
+            Self::De => "de",
+        }
+    }
+
+    /// The ISO 639-3 code of the language the glosses are written in.
+    pub fn gloss_language(self) -> &'static str {
+        match self {
+            Self::En => "eng",
+            Self::Fr => "fra",
+            Self::De => "deu",
+        }
+    }
+
+    /// The edition whose glosses are written in the language with the ISO
+    /// 639-3 code `code`, if there is one.
+    pub fn for_gloss_language(code: &str) -> Option<Self> {
+        [Self::En, Self::Fr, Self::De]
+            .into_iter()
+            .find(|edition| edition.gloss_language() == code)
+    }
+
+    /// The English name of the language the glosses are written in.
+    pub fn gloss_name(self) -> &'static str {
+        match self {
+            Self::En => "English",
+            Self::Fr => "French",
+            Self::De => "German",
+        }
+    }
+
+    /// Where kaikki.org publishes the extracts of this edition.
+    pub fn base_url(self) -> String {
+        match self {
+            Self::En => "https://kaikki.org/dictionary".to_string(),
+            edition => format!("https://kaikki.org/{}wiktionary", edition.code()),
+        }
+    }
+
+    /// Identifies a source of this edition in the cache index, e.g.
+    /// `wiktionary/eng` or `wiktionary/fr/eng`.
+    pub fn source(self, name: &str) -> String {
+        match self {
+            Self::En => format!("wiktionary/{}", name),
+            edition => format!("wiktionary/{}/{}", edition.code(), name),
+        }
+    }
+
+    /// The file name (without extension) a dictionary of `language` is
+    /// written to by default. English glosses are implied, other gloss
+    /// languages are added, e.g. `fra-fra` for the French Wiktionary's
+    /// French dictionary.
+    pub fn output_name(self, language: &str) -> String {
+        match self {
+            Self::En => language.to_string(),
+            edition => format!("{}-{}", language, edition.gloss_language()),
+        }
+    }
+
+    /// The display name of a dictionary of the language called `name`,
+    /// e.g. "French Wiktionary" or "French-German Wiktionary".
+    pub fn dictionary_name(self, name: &str) -> String {
+        match self {
+            Self::En => format!("{} Wiktionary", name),
+            edition => format!("{}-{} Wiktionary", name, edition.gloss_name()),
+        }
+    }
+
+    /// Rewrites a record of this edition into the shape of the English
+    /// edition's records, which the schema describes.
+    pub fn adapt(self, record: &mut Value) {
+        match self {
+            Self::En => {}
+            Self::Fr => adapt_fr(record),
+            Self::De => adapt_de(record),
+        }
+    }
+}
+
+impl fmt::Display for Edition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Edition {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "en" => Ok(Self::En),
+            "fr" => Ok(Self::Fr),
+            "de" => Ok(Self::De),
+            _ => Err(format!(
+                "unknown Wiktionary edition \"{}\", expected en, fr or de",
+                s
+            )),
+        }
+    }
+}
+
+/// The French edition's records differ from the English ones in that:
+///
+/// - etymologies are a list of paragraphs (`etymology_texts`)
+/// - sense categories are plain strings
+/// - translations name their language in `lang_code`
+/// - example translations are in `translation`
+fn adapt_fr(record: &mut Value) {
+    let Some(record) = record.as_object_mut() else {
+        return;
+    };
+
+    join_etymology_texts(record);
+    rename_translation_codes(record);
+
+    for sense in objects(record.get_mut("senses")) {
+        name_categories(sense);
+        rename_translation_codes(sense);
+
+        for example in objects(sense.get_mut("examples")) {
+            rename(example, "translation", "english");
+        }
+    }
+}
+
+/// The German edition's records differ from the English ones like the
+/// French ones do, and also in that:
+///
+/// - example references are objects of title, author, date and so on
+/// - hyphenation is a single string
+fn adapt_de(record: &mut Value) {
+    if let Some(object) = record.as_object_mut()
+        && let Some(Value::String(hyphenation)) = object.get("hyphenation")
+    {
+        let hyphenation = Value::String(hyphenation.clone());
+
+        object.insert("hyphenation".to_string(), Value::Array(vec![hyphenation]));
+    }
+
+    adapt_fr(record);
+
+    let Some(record) = record.as_object_mut() else {
+        return;
+    };
+
+    for sense in objects(record.get_mut("senses")) {
+        for example in objects(sense.get_mut("examples")) {
+            if let Some(Value::Object(reference)) = example.get("ref") {
+                let reference = reference
+                    .values()
+                    .filter_map(Value::as_str)
+                    .collect::<Vec<_>>()
+                    .join(", ");
+
+                example.insert("ref".to_string(), Value::String(reference));
+            }
+        }
+    }
+}
+
+/// The objects in the array `value`, if it is one.
+fn objects(value: Option<&mut Value>) -> impl Iterator<Item = &mut Map<String, Value>> {
+    value
+        .and_then(Value::as_array_mut)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_object_mut)
+}
+
+fn rename(object: &mut Map<String, Value>, from: &str, to: &str) {
+    if !object.contains_key(to)
+        && let Some(value) = object.remove(from)
+    {
+        object.insert(to.to_string(), value);
+    }
+}
+
+fn join_etymology_texts(record: &mut Map<String, Value>) {
+    if let Some(Value::Array(texts)) = record.remove("etymology_texts") {
+        let text = texts
+            .iter()
+            .filter_map(Value::as_str)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        record
+            .entry("etymology_text")
+            .or_insert(Value::String(text));
+    }
+}
+
+fn name_categories(sense: &mut Map<String, Value>) {
+    if let Some(Value::Array(categories)) = sense.get_mut("categories") {
+        for category in categories {
+            if let Value::String(name) = category {
+                let mut named = Map::new();
+
+                named.insert("name".to_string(), Value::String(std::mem::take(name)));
+                *category = Value::Object(named);
+            }
+        }
+    }
+}
+
+fn rename_translation_codes(object: &mut Map<String, Value>) {
+    for translation in objects(object.get_mut("translations")) {
+        rename(translation, "lang_code", "code");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::processors::wiktionary::schema::WiktionaryEntry;
+
+    #[test]
+    fn adapts_french_records() {
+        let mut record = json!({
+            "word": "chien",
+            "lang": "Français",
+            "lang_code": "fr",
+            "pos": "noun",
+            "etymology_texts": ["Du latin canis.", "Voir aussi chienne."],
+            "senses": [{
+                "glosses": ["Mammifère carnivore domestique."],
+                "categories": ["Mammifères en français"],
+                "examples": [{ "text": "Le chien aboie.", "translation": "The dog barks." }],
+                "translations": [{ "lang_code": "en", "lang": "Anglais", "word": "dog" }]
+            }],
+            "translations": [{ "lang_code": "de", "lang": "Allemand", "word": "Hund" }]
+        });
+
+        Edition::Fr.adapt(&mut record);
+
+        assert_eq!(
+            record,
+            json!({
+                "word": "chien",
+                "lang": "Français",
+                "lang_code": "fr",
+                "pos": "noun",
+                "etymology_text": "Du latin canis.\nVoir aussi chienne.",
+                "senses": [{
+                    "glosses": ["Mammifère carnivore domestique."],
+                    "categories": [{ "name": "Mammifères en français" }],
+                    "examples": [{ "text": "Le chien aboie.", "english": "The dog barks." }],
+                    "translations": [{ "code": "en", "lang": "Anglais", "word": "dog" }]
+                }],
+                "translations": [{ "code": "de", "lang": "Allemand", "word": "Hund" }]
+            })
+        );
+        // The adapted record fits the schema
+        serde_json::from_value::<WiktionaryEntry>(record).unwrap();
+    }
+
+    #[test]
+    fn adapts_german_records() {
+        let mut record = json!({
+            "word": "Hund",
+            "lang": "Deutsch",
+            "lang_code": "de",
+            "pos": "noun",
+            "hyphenation": "Hund",
+            "senses": [{
+                "glosses": ["Haustier"],
+                "categories": ["Tier"],
+                "examples": [{
+                    "text": "Der Hund bellt.",
+                    "ref": { "author": "Theodor Fontane", "title": "Effi Briest" }
+                }],
+                "translations": [{ "lang_code": "en", "lang": "Englisch", "word": "dog" }]
+            }]
+        });
+
+        Edition::De.adapt(&mut record);
+
+        assert_eq!(
+            record,
+            json!({
+                "word": "Hund",
+                "lang": "Deutsch",
+                "lang_code": "de",
+                "pos": "noun",
+                "hyphenation": ["Hund"],
+                "senses": [{
+                    "glosses": ["Haustier"],
+                    "categories": [{ "name": "Tier" }],
+                    "examples": [{
+                        "text": "Der Hund bellt.",
+                        "ref": "Theodor Fontane, Effi Briest"
+                    }],
+                    "translations": [{ "code": "en", "lang": "Englisch", "word": "dog" }]
+                }]
+            })
+        );
+        // The adapted record fits the schema
+        serde_json::from_value::<WiktionaryEntry>(record).unwrap();
+    }
+
+    #[test]
+    fn keeps_english_fields() {
+        let mut record = json!({
+            "word": "chat",
+            "etymology_text": "Du latin cattus.",
+            "etymology_texts": ["Autre chose."],
+            "senses": [{
+                "examples": [{ "translation": "A cat.", "english": "The cat." }]
+            }]
+        });
+
+        Edition::Fr.adapt(&mut record);
+
+        assert_eq!(record["etymology_text"], "Du latin cattus.");
+        assert_eq!(record["senses"][0]["examples"][0]["english"], "The cat.");
+        assert_eq!(record["senses"][0]["examples"][0]["translation"], "A cat.");
+    }
+}
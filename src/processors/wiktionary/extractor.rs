@@ -1,47 +1,386 @@
-use console::Term;
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
 use rayon::prelude::*;
+use serde_json::error::Category;
+
+use crate::{
+    error::Error,
+    processors::traits::Extractor,
+    report::{ProgressKind, Reporter},
+    utils::raw_lines,
+};
+
+use super::{Edition, WiktionaryOptions, audit::SchemaAudit, schema::WiktionaryEntry};
+
+pub struct WiktionaryExtractor {
+    edition: Edition,
+    tolerant: bool,
+    max_error_rate: Option<f64>,
+    rejected_path: Option<PathBuf>,
+    audit: Option<SchemaAudit>,
+}
+
+/// A line that didn't parse, as it was read.
+pub(super) struct Rejected {
+    index: usize,
+    kind: &'static str,
+    line: Vec<u8>,
+}
+
+impl WiktionaryExtractor {
+    pub fn with_options(options: &WiktionaryOptions) -> anyhow::Result<Self> {
+        Ok(Self {
+            edition: options.edition,
+            tolerant: options.tolerant,
+            max_error_rate: options.max_error_rate,
+            rejected_path: options.rejected.clone(),
+            audit: options.schema_audit.then(SchemaAudit::new).transpose()?,
+        })
+    }
 
-use crate::{processors::traits::Extractor, progress::STYLE_PROGRESS};
+    /// Parses the line at `index` (counted from 0), which in tolerant mode
+    /// is rejected instead of failing if it doesn't parse.
+    ///
+    /// A line that isn't valid UTF-8 is decoded lossily, except in tolerant
+    /// mode, where it is rejected so that it can be looked at as it was.
+    pub(super) fn parse(
+        &self,
+        index: usize,
+        line: &[u8],
+    ) -> anyhow::Result<Result<WiktionaryEntry, Rejected>> {
+        let text = match std::str::from_utf8(line) {
+            Ok(text) => Cow::Borrowed(text),
+            Err(_) if self.tolerant => {
+                return Ok(Err(Rejected {
+                    index,
+                    kind: "invalid UTF-8",
+                    line: line.to_vec(),
+                }));
+            }
+            Err(_) => String::from_utf8_lossy(line),
+        };
 
-use super::schema::WiktionaryEntry;
+        match self.deserialize(&text) {
+            Ok(entry) => Ok(Ok(entry)),
+            Err(e) if self.tolerant => Ok(Err(Rejected {
+                index,
+                kind: error_kind(&e),
+                line: line.to_vec(),
+            })),
+            Err(e) => Err(Error::Parse {
+                line: index + 1,
+                source: anyhow::anyhow!("{} - {}", e, text),
+            }
+            .into()),
+        }
+    }
+
+    fn deserialize(&self, line: &str) -> serde_json::Result<WiktionaryEntry> {
+        match self.edition {
+            Edition::En => {
+                if let Some(audit) = &self.audit {
+                    audit.record(line);
+                }
+
+                serde_json::from_str(line)
+            }
+            // Other editions are adapted to the English schema first
+            edition => serde_json::from_str(line).and_then(|mut record| {
+                edition.adapt(&mut record);
 
-pub struct WiktionaryExtractor {}
+                if let Some(audit) = &self.audit {
+                    audit.record_value(record.clone());
+                }
+
+                serde_json::from_value(record)
+            }),
+        }
+    }
+
+    /// Reports the schema audit and the lines rejected out of `total`.
+    pub(super) fn finish(
+        &self,
+        reporter: &dyn Reporter,
+        rejected: &[Rejected],
+        total: usize,
+    ) -> anyhow::Result<()> {
+        if let Some(audit) = &self.audit {
+            audit.report(reporter);
+        }
+
+        if self.tolerant {
+            self.handle_rejected(reporter, rejected, total)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reports the rejected lines, writes them to the side file and fails
+    /// if there are more of them than allowed.
+    fn handle_rejected(
+        &self,
+        reporter: &dyn Reporter,
+        rejected: &[Rejected],
+        total: usize,
+    ) -> anyhow::Result<()> {
+        if let Some(path) = &self.rejected_path {
+            write_rejected(path, rejected).map_err(|e| Error::Write {
+                path: path.clone(),
+                source: e,
+            })?;
+        }
+
+        if rejected.is_empty() {
+            return Ok(());
+        }
+
+        let mut kinds: HashMap<&str, usize> = HashMap::new();
+
+        for r in rejected {
+            *kinds.entry(r.kind).or_default() += 1;
+        }
+
+        let mut kinds: Vec<_> = kinds.into_iter().collect();
+
+        kinds.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+
+        let breakdown = kinds
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind))
+            .collect::<Vec<_>>()
+            .join(", ");
+
+        reporter.warn(&format!(
+            "Skipped {} of {} records that couldn't be parsed ({})",
+            rejected.len(),
+            total,
+            breakdown
+        ));
+
+        if let Some(path) = &self.rejected_path {
+            reporter.warn(&format!("Rejected lines written to {}", path.display()));
+        }
+
+        if let Some(max_rate) = self.max_error_rate
+            && self.exceeds_error_rate(rejected.len(), total)
+        {
+            return Err(Error::Rejected {
+                rejected: rejected.len(),
+                total,
+                max_rate,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+
+    /// Whether `rejected` lines out of `total` are more than tolerant mode
+    /// allows.
+    pub(super) fn exceeds_error_rate(&self, rejected: usize, total: usize) -> bool {
+        self.tolerant
+            && self
+                .max_error_rate
+                .is_some_and(|max_rate| rejected as f64 / total as f64 > max_rate)
+    }
+}
+
+/// Names the kind of a serde error, e.g. `invalid type` or `missing field`.
+fn error_kind(error: &serde_json::Error) -> &'static str {
+    match error.classify() {
+        Category::Io => "I/O error",
+        Category::Syntax => "syntax error",
+        Category::Eof => "truncated record",
+        Category::Data => {
+            let message = error.to_string();
+
+            [
+                "invalid type",
+                "invalid value",
+                "invalid length",
+                "unknown variant",
+                "unknown field",
+                "missing field",
+                "duplicate field",
+            ]
+            .into_iter()
+            .find(|kind| message.starts_with(kind))
+            .unwrap_or("invalid data")
+        }
+    }
+}
+
+fn write_rejected(path: &Path, rejected: &[Rejected]) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut rejected: Vec<_> = rejected.iter().collect();
+
+    // The dump rejects lines as it gets to their language, so restore the
+    // source order
+    rejected.sort_by_key(|r| r.index);
+
+    for r in rejected {
+        writer.write_all(&r.line)?;
+        writer.write_all(b"\n")?;
+    }
+
+    writer.flush()?;
+
+    Ok(())
+}
 
 impl Extractor for WiktionaryExtractor {
     type Entry = WiktionaryEntry;
 
-    fn extract(&self, term: &Term, data: &Vec<u8>) -> anyhow::Result<Vec<WiktionaryEntry>> {
-        term.write_line("🔍 Extracting the dictionary...")?;
+    fn extract<R: BufRead + Send>(
+        &self,
+        reporter: &dyn Reporter,
+        reader: R,
+    ) -> anyhow::Result<Vec<WiktionaryEntry>> {
+        reporter.step("Extracting the dictionary");
 
-        let text = String::from_utf8_lossy(data);
+        let progress = reporter.progress(ProgressKind::Lines);
 
-        let progress = indicatif::ProgressBar::new(text.lines().count() as u64);
+        // Lines are read one at a time from disk, so only the parsed entries are kept in memory
+        let result: anyhow::Result<Vec<(usize, Result<WiktionaryEntry, Rejected>)>> =
+            raw_lines(reader)
+                .enumerate()
+                .par_bridge()
+                .map(|(i, line)| {
+                    let line = line?;
+                    progress.inc(1);
+                    Ok((i, self.parse(i, &line)?))
+                })
+                .collect();
 
-        progress.set_style(STYLE_PROGRESS.clone());
+        drop(progress);
 
-        let result: Result<Vec<_>, _> = text
-            .lines()
-            .enumerate()
-            .par_bridge()
-            .map(|(i, l)| {
-                progress.inc(1);
-                serde_json::from_str(l)
-                    .map_err(|e| anyhow::anyhow!("Failed to parse line {}: {} - {}", i + 1, e, l))
-            })
-            .collect();
+        reporter.done("Extraction complete");
+
+        // Lines are parsed out of order, so restore the source order
+        let mut results = result?;
 
-        progress.finish_and_clear();
+        results.sort_unstable_by_key(|(i, _)| *i);
 
-        term.clear_last_lines(1)?;
-        term.write_line("✅ Extraction complete")?;
+        let total = results.len();
+        let mut entries = Vec::with_capacity(total);
+        let mut rejected = vec![];
 
-        Ok(result?)
+        for (_, result) in results {
+            match result {
+                Ok(entry) => entries.push(entry),
+                Err(r) => rejected.push(r),
+            }
+        }
+
+        self.finish(reporter, &rejected, total)?;
+
+        Ok(entries)
     }
 
     fn new() -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        Ok(Self {})
+        Self::with_options(&WiktionaryOptions::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::report::Silent;
+
+    const GOOD: &[u8] = br#"{"word": "dog", "lang": "English", "lang_code": "en"}"#;
+    const MISSING_FIELD: &[u8] = br#"{"word": "cat", "lang": "English"}"#;
+    const INVALID_UTF8: &[u8] =
+        b"{\"word\": \"d\xffg\", \"lang\": \"English\", \"lang_code\": \"en\"}";
+
+    /// Extracts `lines` in tolerant mode, writing the rejected lines to
+    /// `rejected`.
+    fn extract_tolerant(
+        lines: &[&[u8]],
+        rejected: &Path,
+        max_error_rate: Option<f64>,
+    ) -> anyhow::Result<Vec<WiktionaryEntry>> {
+        let extractor = WiktionaryExtractor::with_options(&WiktionaryOptions {
+            tolerant: true,
+            max_error_rate,
+            rejected: Some(rejected.to_path_buf()),
+            ..Default::default()
+        })?;
+
+        extractor.extract(&Silent, Cursor::new(lines.join(&b'\n')))
+    }
+
+    #[test]
+    fn decodes_invalid_utf8_lossily() {
+        let entries = WiktionaryExtractor::new()
+            .unwrap()
+            .extract(&Silent, Cursor::new([GOOD, INVALID_UTF8].join(&b'\n')))
+            .unwrap();
+
+        assert_eq!(
+            entries.iter().map(|e| e.word.as_str()).collect::<Vec<_>>(),
+            ["dog", "d\u{fffd}g"]
+        );
+    }
+
+    #[test]
+    fn rejects_bad_lines_in_tolerant_mode() {
+        let dir = TempDir::new("extractor").unwrap();
+        let rejected = dir.path().join("rejected.jsonl");
+        let entries = extract_tolerant(
+            &[GOOD, MISSING_FIELD, GOOD, INVALID_UTF8],
+            &rejected,
+            Some(0.5),
+        )
+        .unwrap();
+
+        assert_eq!(entries.len(), 2);
+        assert_eq!(
+            std::fs::read(&rejected).unwrap(),
+            [MISSING_FIELD, b"\n", INVALID_UTF8, b"\n"].concat()
+        );
+    }
+
+    #[test]
+    fn fails_above_the_error_rate() {
+        let dir = TempDir::new("extractor").unwrap();
+        let rejected = dir.path().join("rejected.jsonl");
+        let error = extract_tolerant(
+            &[GOOD, MISSING_FIELD, GOOD, INVALID_UTF8],
+            &rejected,
+            Some(0.25),
+        )
+        .unwrap_err();
+
+        assert!(matches!(
+            Error::find(&error),
+            Some(Error::Rejected {
+                rejected: 2,
+                total: 4,
+                ..
+            })
+        ));
+
+        // The rejected lines are still written for a look at what went wrong
+        assert_eq!(
+            std::fs::read(&rejected).unwrap(),
+            [MISSING_FIELD, b"\n", INVALID_UTF8, b"\n"].concat()
+        );
     }
 }
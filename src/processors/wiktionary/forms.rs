@@ -0,0 +1,194 @@
+//! What kind of form a Wiktionary form is, going by its tags.
+
+use odict::FormKind;
+
+use super::schema::WordForm;
+
+/// Tags of forms that are really part of how Wiktionary lays out its
+/// inflection tables, not forms of the word.
+const PSEUDO_FORM_TAGS: [&str; 5] = [
+    "table-tags",
+    "inflection-template",
+    "class",
+    "canonical",
+    "romanization",
+];
+
+/// Forms with all of these tags are of this kind, checked in order.
+const FORM_KINDS: [(&[&str], &str); 11] = [
+    (&["superlative"], "superlative"),
+    (&["comparative"], "comparative"),
+    (&["past", "participle"], "past participle"),
+    (&["present", "participle"], "present participle"),
+    (&["participle"], "participle"),
+    (&["gerund"], "gerund"),
+    (&["infinitive"], "infinitive"),
+    (&["imperative"], "imperative"),
+    (&["past"], "past"),
+    (&["plural"], "plural"),
+    (&["singular"], "singular"),
+];
+
+/// Tags of verb forms that have no kind of their own.
+const CONJUGATION_TAGS: [&str; 11] = [
+    "first-person",
+    "second-person",
+    "third-person",
+    "present",
+    "future",
+    "preterite",
+    "imperfect",
+    "perfect",
+    "indicative",
+    "subjunctive",
+    "conditional",
+];
+
+/// Tags of the cases and genders nouns and adjectives are inflected for.
+const INFLECTION_TAGS: [&str; 13] = [
+    "nominative",
+    "accusative",
+    "genitive",
+    "dative",
+    "instrumental",
+    "locative",
+    "prepositional",
+    "vocative",
+    "ablative",
+    "partitive",
+    "masculine",
+    "feminine",
+    "neuter",
+];
+
+/// Whether `form` is a structural pseudo-form rather than a form of the
+/// word, e.g. the name of its inflection template.
+pub fn is_pseudo_form(form: &WordForm) -> bool {
+    let form_text = form.form.trim();
+
+    form_text.is_empty()
+        || form_text == "-"
+        || form
+            .tags
+            .iter()
+            .any(|tag| PSEUDO_FORM_TAGS.contains(&tag.as_str()))
+}
+
+/// The kind of a form with `tags`, if they say.
+///
+/// Person, tense and mood make a verb form a conjugation, unless it has a
+/// kind of its own like "past participle"; a verb's third-person singular
+/// is a conjugation, not a singular. Case and gender make a form an
+/// inflection, unless it is a plural: the genitive singular is an
+/// inflection, the genitive plural is a plural.
+pub fn form_kind(tags: &[String]) -> Option<FormKind> {
+    let has = |tag: &str| tags.iter().any(|t| t == tag);
+    let kind = FORM_KINDS
+        .iter()
+        .find(|(required, _)| required.iter().all(|tag| has(tag)))
+        .map(|(_, kind)| *kind);
+
+    match kind {
+        Some("superlative") => Some(FormKind::Superlative),
+        Some("comparative") => Some(FormKind::Comparative),
+        Some("plural" | "singular") if CONJUGATION_TAGS.iter().any(|tag| has(tag)) => {
+            Some(FormKind::Conjugation)
+        }
+        Some("plural") => Some(FormKind::Plural),
+        Some("singular") if INFLECTION_TAGS.iter().any(|tag| has(tag)) => {
+            Some(FormKind::Inflection)
+        }
+        Some("singular") => Some(FormKind::Singular),
+        Some(kind) => Some(FormKind::Other(kind.to_string())),
+        None if CONJUGATION_TAGS.iter().any(|tag| has(tag)) => Some(FormKind::Conjugation),
+        None if INFLECTION_TAGS.iter().any(|tag| has(tag)) => Some(FormKind::Inflection),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kind(tags: &[&str]) -> Option<FormKind> {
+        form_kind(&tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>())
+    }
+
+    fn form(form: &str, tags: &[&str]) -> WordForm {
+        serde_json::from_value(serde_json::json!({ "form": form, "tags": tags })).unwrap()
+    }
+
+    #[test]
+    fn verb_forms_are_conjugations() {
+        assert_eq!(
+            kind(&["third-person", "singular", "present"]),
+            Some(FormKind::Conjugation)
+        );
+        assert_eq!(
+            kind(&["first-person", "plural", "indicative", "future"]),
+            Some(FormKind::Conjugation)
+        );
+        assert_eq!(
+            kind(&["subjunctive", "imperfect"]),
+            Some(FormKind::Conjugation)
+        );
+    }
+
+    #[test]
+    fn participles_keep_their_own_kind() {
+        assert_eq!(
+            kind(&["past", "participle"]),
+            Some(FormKind::Other("past participle".to_string()))
+        );
+        assert_eq!(
+            kind(&["present", "participle"]),
+            Some(FormKind::Other("present participle".to_string()))
+        );
+        assert_eq!(
+            kind(&["participle", "perfect"]),
+            Some(FormKind::Other("participle".to_string()))
+        );
+        assert_eq!(kind(&["past"]), Some(FormKind::Other("past".to_string())));
+    }
+
+    #[test]
+    fn plurals_win_over_cases() {
+        assert_eq!(kind(&["plural"]), Some(FormKind::Plural));
+        assert_eq!(kind(&["genitive", "plural"]), Some(FormKind::Plural));
+        assert_eq!(kind(&["singular"]), Some(FormKind::Singular));
+    }
+
+    #[test]
+    fn cases_and_genders_are_inflections() {
+        assert_eq!(kind(&["genitive", "singular"]), Some(FormKind::Inflection));
+        assert_eq!(kind(&["feminine"]), Some(FormKind::Inflection));
+        assert_eq!(kind(&["dative", "neuter"]), Some(FormKind::Inflection));
+    }
+
+    #[test]
+    fn degrees_of_comparison() {
+        assert_eq!(kind(&["comparative"]), Some(FormKind::Comparative));
+        assert_eq!(
+            kind(&["superlative", "plural"]),
+            Some(FormKind::Superlative)
+        );
+    }
+
+    #[test]
+    fn other_tags_have_no_kind() {
+        assert_eq!(kind(&[]), None);
+        assert_eq!(kind(&["alternative", "obsolete"]), None);
+    }
+
+    #[test]
+    fn pseudo_forms() {
+        assert!(is_pseudo_form(&form("", &["plural"])));
+        assert!(is_pseudo_form(&form(" - ", &[])));
+        assert!(is_pseudo_form(&form(
+            "ru-noun-table",
+            &["inflection-template"]
+        )));
+        assert!(is_pseudo_form(&form("no-table-tags", &["table-tags"])));
+        assert!(!is_pseudo_form(&form("dogs", &["plural"])));
+    }
+}
@@ -0,0 +1,163 @@
+//! The index of the languages kaikki.org publishes Wiktionary extracts for.
+
+use regex::Regex;
+
+use crate::{cache::Cache, report::Reporter};
+
+/// Where kaikki.org lists its languages.
+pub const INDEX_URL: &str = "https://kaikki.org/dictionary/index.html";
+
+/// A language in the kaikki.org index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KaikkiLanguage {
+    /// The language's name on kaikki.org, e.g. "Old English"
+    pub name: String,
+    /// How many senses kaikki.org has for the language, if listed
+    pub entries: Option<u64>,
+    /// The size of the language's JSONL extract in bytes, if listed
+    pub size: Option<u64>,
+}
+
+/// Parses the HTML of the kaikki.org language index, in the order the
+/// languages are listed.
+///
+/// Every link to a language page counts as a language, with the counts and
+/// sizes that follow it up to the next tag, e.g.
+/// `<a href="English/index.html">English</a> (1,335,734 senses, 2.6GB)`.
+pub fn parse_index(html: &str) -> anyhow::Result<Vec<KaikkiLanguage>> {
+    let link = Regex::new(r#"<a href="([^"/]+)/index\.html">([^<]+)</a>([^<]*)"#)?;
+    let count = Regex::new(r"([\d,]+)\s+(?:senses|words|entries)")?;
+    let size = Regex::new(r"(?i)([\d.]+)\s*([KMGT]i?B|bytes)\b")?;
+
+    let mut languages: Vec<KaikkiLanguage> = vec![];
+
+    for captures in link.captures_iter(html) {
+        let name = unescape(captures[2].trim());
+        let details = &captures[3];
+
+        if captures[1].starts_with('.') || languages.iter().any(|l| l.name == name) {
+            continue;
+        }
+
+        languages.push(KaikkiLanguage {
+            name,
+            entries: count
+                .captures(details)
+                .and_then(|c| c[1].replace(',', "").parse().ok()),
+            size: size
+                .captures(details)
+                .and_then(|c| parse_size(&c[1], &c[2])),
+        });
+    }
+
+    Ok(languages)
+}
+
+/// Fetches the kaikki.org language index through the cache, or reads it
+/// from `input` if given.
+pub async fn fetch_index(
+    reporter: &dyn Reporter,
+    cache: &Cache,
+    input: Option<&std::path::Path>,
+) -> anyhow::Result<Vec<KaikkiLanguage>> {
+    let description = "language index";
+    let source = "wiktionary/index";
+    let path = match input {
+        Some(input) => cache.use_local(input, INDEX_URL, description, source, reporter)?,
+        None => {
+            cache
+                .fetch(INDEX_URL, description, source, reporter)
+                .await?
+        }
+    };
+
+    parse_index(&std::fs::read_to_string(path)?)
+}
+
+fn parse_size(value: &str, unit: &str) -> Option<u64> {
+    let value: f64 = value.parse().ok()?;
+    let multiplier: u64 = match unit.to_ascii_uppercase().as_str() {
+        "BYTES" => 1,
+        "KB" | "KIB" => 1 << 10,
+        "MB" | "MIB" => 1 << 20,
+        "GB" | "GIB" => 1 << 30,
+        "TB" | "TIB" => 1 << 40,
+        _ => return None,
+    };
+
+    Some((value * multiplier as f64) as u64)
+}
+
+fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A trimmed copy of the kaikki.org index, with its navigation links,
+    /// a language listed twice and one whose name is escaped.
+    const INDEX: &str = include_str!("testdata/kaikki-index.html");
+
+    fn language(name: &str, entries: Option<u64>, size: Option<u64>) -> KaikkiLanguage {
+        KaikkiLanguage {
+            name: name.to_string(),
+            entries,
+            size,
+        }
+    }
+
+    #[test]
+    fn parses_the_index() {
+        let languages = parse_index(INDEX).unwrap();
+
+        assert_eq!(
+            languages,
+            vec![
+                language(
+                    "English",
+                    Some(1_335_734),
+                    Some((2.6 * (1u64 << 30) as f64) as u64)
+                ),
+                language(
+                    "Latin",
+                    Some(862_311),
+                    Some((1.1 * (1u64 << 30) as f64) as u64)
+                ),
+                language(
+                    "Afrikaans",
+                    Some(12_043),
+                    Some((21.4 * (1u64 << 20) as f64) as u64)
+                ),
+                language(
+                    "Old English",
+                    Some(33_561),
+                    Some((58.2 * (1u64 << 20) as f64) as u64)
+                ),
+                language("Pidgin & Creole", Some(412), None),
+                language(
+                    "Tok Pisin",
+                    Some(2_107),
+                    Some((3.9 * (1u64 << 20) as f64) as u64)
+                ),
+                language("Yola", Some(786), Some(940)),
+                language("Zhuang", None, None),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("940", "bytes"), Some(940));
+        assert_eq!(parse_size("1.5", "KB"), Some(1536));
+        assert_eq!(parse_size("2", "MiB"), Some(2 << 20));
+        assert_eq!(parse_size("1", "gb"), Some(1 << 30));
+        assert_eq!(parse_size("1", "PB"), None);
+    }
+}
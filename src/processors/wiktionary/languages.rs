@@ -0,0 +1,229 @@
+use std::{collections::HashMap, path::Path};
+
+use isolang::Language;
+use odict::PartOfSpeech;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::frequency::FrequencySource;
+
+use super::Edition;
+
+/// The profiles that ship with odict-convert.
+const BUILTIN: &str = include_str!("languages.toml");
+
+/// Everything about a language that Wiktionary dictionaries are built with.
+#[derive(Debug, Clone)]
+pub struct LanguageProfile {
+    /// ISO 639-3 code
+    pub code: String,
+    /// ISO 639-1 code, if the language has one
+    pub iso639_1: Option<String>,
+    /// The language's name on kaikki.org
+    pub kaikki: String,
+    /// The language's name in kaikki.org's extracts of other Wiktionary
+    /// editions, where it differs from its English name
+    pub editions: HashMap<Edition, String>,
+    /// The name the dictionary is called by, e.g. "English" for
+    /// "English Wiktionary"
+    pub name: String,
+    pub frequency: FrequencySource,
+    /// Part-of-speech overrides on top of the built-in map
+    pub pos: HashMap<String, PartOfSpeech>,
+    pub normalize: Normalization,
+}
+
+/// Rules applied to headwords, forms and links, e.g. to strip stress marks.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Normalization {
+    /// Regular expressions whose matches are removed
+    pub remove: Vec<String>,
+    /// Regular expressions and what their matches are replaced with
+    pub replace: Vec<(String, String)>,
+}
+
+/// [`Normalization`] rules, compiled.
+#[derive(Debug, Clone, Default)]
+pub struct Normalizer {
+    rules: Vec<(Regex, String)>,
+}
+
+/// A profile as written in a data file, where everything is optional.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProfileEntry {
+    kaikki: Option<String>,
+    editions: HashMap<Edition, String>,
+    iso639_1: Option<String>,
+    aliases: Vec<String>,
+    name: Option<String>,
+    frequency: Option<FrequencySource>,
+    pos: HashMap<String, PartOfSpeech>,
+    normalize: Option<Normalization>,
+}
+
+/// The language profiles from the built-in data file and any added ones.
+#[derive(Debug, Clone, Default)]
+pub struct Languages {
+    profiles: HashMap<String, ProfileEntry>,
+}
+
+impl Languages {
+    /// The built-in profiles.
+    pub fn builtin() -> anyhow::Result<Self> {
+        let profiles = toml::from_str(BUILTIN)
+            .map_err(|e| anyhow::anyhow!("Invalid built-in language profiles: {}", e))?;
+
+        Ok(Self { profiles })
+    }
+
+    /// The built-in profiles, with the ones in the TOML file at `path` (if
+    /// given) applied on top.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let mut languages = Self::builtin()?;
+
+        if let Some(path) = path {
+            let contents = std::fs::read_to_string(path)?;
+            let profiles: HashMap<String, ProfileEntry> =
+                toml::from_str(&contents).map_err(|e| {
+                    anyhow::anyhow!("Invalid language profiles {}: {}", path.display(), e)
+                })?;
+
+            for (code, profile) in profiles {
+                let merged = match languages.profiles.remove(&code) {
+                    Some(builtin) => builtin.merge(profile),
+                    None => profile,
+                };
+
+                languages.profiles.insert(code, merged);
+            }
+        }
+
+        Ok(languages)
+    }
+
+    /// Returns the ISO 639-3 code of the language with the ISO 639-1 or
+    /// 639-3 code `code`, or one of the aliases in its profile.
+    pub fn code(&self, code: &str) -> Option<String> {
+        if self.profiles.contains_key(code) {
+            return Some(code.to_string());
+        }
+
+        let aliased = self
+            .profiles
+            .iter()
+            .find(|(_, profile)| {
+                profile.iso639_1.as_deref() == Some(code)
+                    || profile.aliases.iter().any(|alias| alias == code)
+            })
+            .map(|(code, _)| code.clone());
+
+        aliased.or_else(|| {
+            Language::from_639_3(code)
+                .or_else(|| Language::from_639_1(code))
+                .map(|language| language.to_639_3().to_string())
+        })
+    }
+
+    /// Returns the ISO 639-3 code of the language called `name` on
+    /// kaikki.org, if it has one.
+    pub fn code_for_kaikki(&self, name: &str) -> Option<String> {
+        let profiled = self
+            .profiles
+            .iter()
+            .find(|(_, profile)| profile.kaikki.as_deref() == Some(name))
+            .map(|(code, _)| code.clone());
+
+        profiled.or_else(|| Language::from_name(name).map(|l| l.to_639_3().to_string()))
+    }
+
+    /// Returns the profile of the language with the ISO 639-1 or 639-3 code
+    /// `code`, filling in what its profile (if any) leaves out.
+    pub fn profile(&self, code: &str) -> anyhow::Result<LanguageProfile> {
+        let Some(code) = self.code(code) else {
+            anyhow::bail!(
+                "Unknown language: {} (expected an ISO 639-1 or 639-3 code)",
+                code
+            );
+        };
+
+        let entry = self.profiles.get(&code).cloned().unwrap_or_default();
+        let language = Language::from_639_3(&code);
+        let kaikki = match (entry.kaikki, language) {
+            (Some(kaikki), _) => kaikki,
+            (None, Some(language)) => language.to_name().to_string(),
+            (None, None) => anyhow::bail!("The language profile of {} needs a kaikki name", code),
+        };
+
+        Ok(LanguageProfile {
+            iso639_1: entry
+                .iso639_1
+                .or_else(|| language.and_then(|l| l.to_639_1()).map(str::to_string)),
+            name: entry.name.unwrap_or_else(|| kaikki.clone()),
+            kaikki,
+            editions: entry.editions,
+            frequency: entry.frequency.unwrap_or_default(),
+            pos: entry.pos,
+            normalize: entry.normalize.unwrap_or_default(),
+            code,
+        })
+    }
+}
+
+impl LanguageProfile {
+    /// The language's name in kaikki.org's extracts of `edition`, which is
+    /// its English name unless the profile says otherwise.
+    pub fn kaikki_name(&self, edition: Edition) -> &str {
+        self.editions.get(&edition).unwrap_or(&self.kaikki)
+    }
+}
+
+impl ProfileEntry {
+    /// Applies `other` on top of this profile.
+    fn merge(mut self, other: Self) -> Self {
+        self.pos.extend(other.pos);
+        self.editions.extend(other.editions);
+        self.aliases.extend(other.aliases);
+
+        Self {
+            kaikki: other.kaikki.or(self.kaikki),
+            editions: self.editions,
+            iso639_1: other.iso639_1.or(self.iso639_1),
+            aliases: self.aliases,
+            name: other.name.or(self.name),
+            frequency: other.frequency.or(self.frequency),
+            pos: self.pos,
+            normalize: other.normalize.or(self.normalize),
+        }
+    }
+}
+
+impl Normalization {
+    pub fn compile(&self) -> anyhow::Result<Normalizer> {
+        let removals = self
+            .remove
+            .iter()
+            .map(|pattern| Ok((Regex::new(pattern)?, String::new())));
+        let replacements = self
+            .replace
+            .iter()
+            .map(|(pattern, replacement)| Ok((Regex::new(pattern)?, replacement.clone())));
+
+        Ok(Normalizer {
+            rules: removals
+                .chain(replacements)
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+impl Normalizer {
+    pub fn apply(&self, text: &str) -> String {
+        self.rules
+            .iter()
+            .fold(text.to_string(), |text, (regex, replacement)| {
+                regex.replace_all(&text, replacement.as_str()).into_owned()
+            })
+    }
+}
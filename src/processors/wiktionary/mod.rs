@@ -1,25 +1,83 @@
+//! Wiktionary, from the wiktextract JSONL extracts published on kaikki.org.
+
 use super::Processor;
+use crate::frequency::FrequencySource;
 
+mod audit;
+mod bilingual;
 mod consts;
 mod converter;
 mod downloader;
+mod dump;
+mod editions;
 mod extractor;
-mod schema;
+mod forms;
+mod index;
+mod languages;
+mod options;
+mod pos;
+
+pub mod schema;
+
+pub use bilingual::{WiktionaryBilingualConverter, WiktionaryBilingualProcessor};
+pub use converter::{WiktionaryConverter, prune_cross_references};
+pub use downloader::WiktionaryDownloader;
+pub use editions::Edition;
+pub use extractor::WiktionaryExtractor;
+pub use index::{INDEX_URL, KaikkiLanguage, fetch_index, parse_index};
+pub use languages::{LanguageProfile, Languages, Normalization, Normalizer};
+pub use options::WiktionaryOptions;
+pub use pos::PosMap;
+
+/// Builds a dictionary of one language from its entries in a Wiktionary
+/// edition, English unless chosen otherwise.
+pub struct WiktionaryProcessor {
+    options: WiktionaryOptions,
+    languages: Languages,
+}
 
-pub use consts::SUPPORTED_LANGUAGES;
+impl WiktionaryProcessor {
+    pub fn with_options(options: WiktionaryOptions) -> anyhow::Result<Self> {
+        let languages = Languages::load(options.profiles.as_deref())?;
 
-pub struct WiktionaryProcessor {}
+        Ok(Self { options, languages })
+    }
+}
 
 impl Processor for WiktionaryProcessor {
     type Entry = schema::WiktionaryEntry;
-    type Downloader = downloader::WiktionaryDownloader;
-    type Extractor = extractor::WiktionaryExtractor;
-    type Converter = converter::WiktionaryConverter;
+    type Downloader = WiktionaryDownloader;
+    type Extractor = WiktionaryExtractor;
+    type Converter = WiktionaryConverter;
 
     fn new() -> anyhow::Result<Self>
     where
         Self: Sized,
     {
-        Ok(Self {})
+        Self::with_options(WiktionaryOptions::default())
+    }
+
+    fn downloader(&self, language: &Option<String>) -> anyhow::Result<WiktionaryDownloader> {
+        match language {
+            Some(language) => Ok(WiktionaryDownloader::with_profile(
+                self.languages.profile(language)?,
+                self.options.edition,
+            )),
+            None => anyhow::bail!("A language is required for the Wiktionary downloader"),
+        }
+    }
+
+    fn frequency_source(&self, language: &str) -> FrequencySource {
+        self.languages
+            .profile(language)
+            .map_or(FrequencySource::Auto, |profile| profile.frequency)
+    }
+
+    fn extractor(&self) -> anyhow::Result<WiktionaryExtractor> {
+        WiktionaryExtractor::with_options(&self.options)
+    }
+
+    fn converter(&self) -> anyhow::Result<WiktionaryConverter> {
+        WiktionaryConverter::with_options(&self.options, self.languages.clone())
     }
 }
@@ -0,0 +1,66 @@
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use super::Edition;
+
+/// How a Wiktionary dictionary is built.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WiktionaryOptions {
+    /// The Wiktionary edition, which decides the language of the glosses
+    pub edition: Edition,
+
+    /// Skip records that don't parse instead of failing on the first one
+    pub tolerant: bool,
+
+    /// In tolerant mode, still fail if more than this fraction of the
+    /// records are rejected
+    pub max_error_rate: Option<f64>,
+
+    /// In tolerant mode, where the rejected lines are written
+    pub rejected: Option<PathBuf>,
+
+    /// Report keys the schema doesn't know and values of unexpected types
+    pub schema_audit: bool,
+
+    /// Keep at most this many examples and quotations per sense
+    pub max_examples: Option<usize>,
+
+    /// Leave quotations out, keeping only usage examples
+    pub skip_quotations: bool,
+
+    /// Only keep translations into these languages (ISO 639-1 or 639-3
+    /// codes), or into every language if empty
+    pub translation_languages: Vec<String>,
+
+    /// Add an entry for every inflected form that isn't a headword itself,
+    /// pointing to the headword it is a form of
+    pub form_redirects: bool,
+
+    /// A TOML file extending or overriding the part-of-speech mapping
+    pub pos_map: Option<PathBuf>,
+
+    /// A TOML file of language profiles applied on top of the built-in ones
+    pub profiles: Option<PathBuf>,
+}
+
+impl WiktionaryOptions {
+    /// Resolves the relative paths in the options against `base`.
+    pub fn relative_to(mut self, base: &Path) -> Self {
+        self.rejected = self.rejected.map(|rejected| base.join(rejected));
+        self.pos_map = self.pos_map.map(|pos_map| base.join(pos_map));
+        self.profiles = self.profiles.map(|profiles| base.join(profiles));
+        self
+    }
+
+    /// Fills in the settings that depend on where the dictionary is written,
+    /// e.g. rejected lines go to `<output>.rejected.jsonl` by default.
+    pub fn for_output(mut self, output: &Path) -> Self {
+        if self.tolerant && self.rejected.is_none() {
+            self.rejected = Some(output.with_extension("rejected.jsonl"));
+        }
+
+        self
+    }
+}
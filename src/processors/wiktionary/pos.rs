@@ -0,0 +1,45 @@
+use std::{collections::HashMap, path::Path};
+
+use odict::PartOfSpeech;
+use serde::Deserialize;
+
+/// Maps kaikki part-of-speech values to odict's, on top of the language
+/// profile's overrides and the built-in map.
+///
+/// Read from a TOML file of tables of overrides, one per language code and
+/// `[all]` for every language. Values are odict part-of-speech names (e.g.
+/// `n`, `adj_na` or `phr_adv`); anything else is kept as-is.
+///
+/// ```toml
+/// [all]
+/// adj_noun = "adj"
+///
+/// [jpn]
+/// adj_noun = "adj_na"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PosMap {
+    #[serde(default)]
+    all: HashMap<String, PartOfSpeech>,
+
+    #[serde(flatten)]
+    languages: HashMap<String, HashMap<String, PartOfSpeech>>,
+}
+
+impl PosMap {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+
+        toml::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Invalid POS map {}: {}", path.display(), e))
+    }
+
+    /// Resolves `value` for `language`, preferring the language's overrides
+    /// to the ones for every language.
+    pub fn get(&self, language: Option<&str>, value: &str) -> Option<&PartOfSpeech> {
+        language
+            .and_then(|language| self.languages.get(language))
+            .and_then(|overrides| overrides.get(value))
+            .or_else(|| self.all.get(value))
+    }
+}
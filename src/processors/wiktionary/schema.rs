@@ -51,6 +51,9 @@ pub struct WiktionaryEntry {
     /// Non-disambiguated hypernyms
     #[serde(default)]
     pub hypernyms: Vec<WordLink>,
+    /// Non-disambiguated hyponyms
+    #[serde(default)]
+    pub hyponyms: Vec<WordLink>,
     /// Non-disambiguated holonyms
     #[serde(default)]
     pub holonyms: Vec<WordLink>,
@@ -132,6 +135,9 @@ pub struct WordSense {
     /// Sense-disambiguated hypernyms
     #[serde(default)]
     pub hypernyms: Vec<WordLink>,
+    /// Sense-disambiguated hyponyms
+    #[serde(default)]
+    pub hyponyms: Vec<WordLink>,
     /// Sense-disambiguated holonyms
     #[serde(default)]
     pub holonyms: Vec<WordLink>,
@@ -313,14 +319,14 @@ pub struct Example {
     /// Example text
     #[serde(default)]
     pub text: Option<String>,
-    /// Source reference
-    #[serde(rename = "ref")]
+    /// Source reference, for quotations
+    #[serde(default, rename = "ref")]
     pub reference: Option<String>,
     /// English translation
     #[serde(default)]
     pub english: Option<String>,
     /// Example type (example or quotation)
-    #[serde(default)]
+    #[serde(default, rename = "type")]
     pub type_: Option<String>,
     /// Romanization
     #[serde(default)]
@@ -329,3 +335,84 @@ pub struct Example {
     #[serde(default)]
     pub note: Option<String>,
 }
+
+impl Example {
+    /// Whether this is a quotation from a source rather than a usage
+    /// example. Older extracts only mark quotations by their reference.
+    pub fn is_quotation(&self) -> bool {
+        match self.type_.as_deref() {
+            Some(kind) => matches!(kind, "quotation" | "quote"),
+            None => self.reference.is_some(),
+        }
+    }
+}
+
+impl WiktionaryEntry {
+    /// The words this entry is related to, as a whole, by the name of the
+    /// relation (e.g. `synonym`).
+    pub fn relations(&self) -> impl Iterator<Item = (&'static str, &WordLink)> {
+        relations([
+            &self.synonyms,
+            &self.antonyms,
+            &self.hypernyms,
+            &self.hyponyms,
+            &self.holonyms,
+            &self.meronyms,
+            &self.coordinate_terms,
+            &self.derived,
+            &self.related,
+        ])
+    }
+}
+
+impl WordSense {
+    /// The lemmas this sense is a form of, by the kind of form: an
+    /// `inflection` (`form_of`) or an `alternative` spelling (`alt_of`).
+    pub fn lemmas(&self) -> impl Iterator<Item = (&'static str, &FormOf)> {
+        LEMMA_KINDS
+            .into_iter()
+            .zip([&self.form_of, &self.alt_of])
+            .flat_map(|(kind, links)| links.iter().map(move |link| (kind, link)))
+    }
+
+    /// The words this sense is related to, by the name of the relation
+    /// (e.g. `synonym`).
+    pub fn relations(&self) -> impl Iterator<Item = (&'static str, &WordLink)> {
+        relations([
+            &self.synonyms,
+            &self.antonyms,
+            &self.hypernyms,
+            &self.hyponyms,
+            &self.holonyms,
+            &self.meronyms,
+            &self.coordinate_terms,
+            &self.derived,
+            &self.related,
+        ])
+    }
+}
+
+/// The names of the relations between words, in the order
+/// [`WiktionaryEntry::relations`] and [`WordSense::relations`] list them.
+pub const RELATIONS: [&str; 9] = [
+    "synonym",
+    "antonym",
+    "hypernym",
+    "hyponym",
+    "holonym",
+    "meronym",
+    "coordinate",
+    "derived",
+    "related",
+];
+
+/// The kinds of form a sense can be of its lemma, in the order of
+/// [`WordSense::lemmas`].
+pub const LEMMA_KINDS: [&str; 2] = ["inflection", "alternative"];
+
+fn relations(links: [&Vec<WordLink>; 9]) -> impl Iterator<Item = (&'static str, &WordLink)> {
+    RELATIONS
+        .into_iter()
+        .zip(links)
+        .flat_map(|(relation, links)| links.iter().map(move |link| (relation, link)))
+}
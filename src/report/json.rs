@@ -0,0 +1,145 @@
+use std::{
+    io::Write,
+    sync::{
+        Arc, Mutex, PoisonError,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde_json::{Value, json};
+
+use super::{Progress, ProgressKind, Reporter};
+
+/// How often a progress event is emitted while a step is running.
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+type SharedWriter = Arc<Mutex<Box<dyn Write + Send>>>;
+
+/// Reports every event as a JSON object on its own line, for other programs
+/// to consume, e.g. `{"event":"done","message":"Extraction complete"}`.
+///
+/// Progress is reported as `progress` events with the `kind` of progress,
+/// the current `position` and the `total` if known, at most once a second
+/// and once more when the step finishes.
+pub struct JsonReporter {
+    writer: SharedWriter,
+}
+
+impl JsonReporter {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Arc::new(Mutex::new(Box::new(writer))),
+        }
+    }
+}
+
+impl Default for JsonReporter {
+    fn default() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+fn emit(writer: &SharedWriter, event: Value) {
+    let mut writer = writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+    // Logging is best-effort, a closed pipe shouldn't fail a build
+    let _ = writeln!(writer, "{}", event);
+}
+
+impl Reporter for JsonReporter {
+    fn step(&self, message: &str) {
+        emit(&self.writer, json!({ "event": "step", "message": message }));
+    }
+
+    fn done(&self, message: &str) {
+        emit(&self.writer, json!({ "event": "done", "message": message }));
+    }
+
+    fn warn(&self, message: &str) {
+        emit(
+            &self.writer,
+            json!({ "event": "warning", "message": message }),
+        );
+    }
+
+    fn progress(&self, kind: ProgressKind) -> Box<dyn Progress> {
+        Box::new(JsonProgress {
+            writer: self.writer.clone(),
+            kind,
+            position: AtomicU64::new(0),
+            message: Mutex::new(String::new()),
+            last_emitted: Mutex::new(Instant::now()),
+        })
+    }
+}
+
+struct JsonProgress {
+    writer: SharedWriter,
+    kind: ProgressKind,
+    position: AtomicU64,
+    message: Mutex<String>,
+    last_emitted: Mutex<Instant>,
+}
+
+impl JsonProgress {
+    fn emit(&self) {
+        let (kind, total) = match self.kind {
+            ProgressKind::Lines => ("lines", None),
+            ProgressKind::Items(total) => ("items", Some(total)),
+            ProgressKind::Bytes(total) => ("bytes", Some(total)),
+            ProgressKind::Spinner => ("spinner", None),
+        };
+
+        let message = self.message.lock().unwrap_or_else(PoisonError::into_inner);
+
+        emit(
+            &self.writer,
+            json!({
+                "event": "progress",
+                "kind": kind,
+                "position": self.position.load(Ordering::Relaxed),
+                "total": total,
+                "message": *message,
+            }),
+        );
+    }
+
+    fn tick(&self) {
+        // Progress is ticked from many threads at once, so skip the check
+        // rather than wait for it while another thread is emitting
+        let Ok(mut last_emitted) = self.last_emitted.try_lock() else {
+            return;
+        };
+
+        if last_emitted.elapsed() >= PROGRESS_INTERVAL {
+            *last_emitted = Instant::now();
+            self.emit();
+        }
+    }
+}
+
+impl Progress for JsonProgress {
+    fn inc(&self, delta: u64) {
+        self.position.fetch_add(delta, Ordering::Relaxed);
+        self.tick();
+    }
+
+    fn set_position(&self, position: u64) {
+        self.position.store(position, Ordering::Relaxed);
+        self.tick();
+    }
+
+    fn set_message(&self, message: &str) {
+        let mut current = self.message.lock().unwrap_or_else(PoisonError::into_inner);
+
+        current.clear();
+        current.push_str(message);
+    }
+}
+
+impl Drop for JsonProgress {
+    fn drop(&mut self) {
+        self.emit();
+    }
+}
@@ -0,0 +1,113 @@
+//! How the pipeline tells its caller what it is doing.
+//!
+//! Every stage reports through a [`Reporter`] instead of writing to the
+//! terminal itself. [`TermReporter`] shows status lines and progress bars on
+//! an interactive terminal, [`PlainReporter`] logs plain lines for CI,
+//! [`JsonReporter`] emits JSON-lines events for other programs and
+//! [`Silent`] discards everything.
+
+mod json;
+mod plain;
+mod styles;
+mod term;
+
+pub use json::JsonReporter;
+pub use plain::PlainReporter;
+pub use term::TermReporter;
+
+/// Receives status updates and progress from the pipeline.
+pub trait Reporter: Send + Sync {
+    /// A step started, e.g. "Extracting the dictionary".
+    fn step(&self, message: &str);
+
+    /// A step finished or something was done, e.g. "Extraction complete".
+    fn done(&self, message: &str);
+
+    /// Something went wrong, but the pipeline carries on.
+    fn warn(&self, message: &str);
+
+    /// Starts tracking the progress of the current step.
+    fn progress(&self, kind: ProgressKind) -> Box<dyn Progress>;
+}
+
+/// What a [`Progress`] counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressKind {
+    /// Lines read from a source file of unknown length
+    Lines,
+    /// Items out of a known total
+    Items(u64),
+    /// Bytes out of a known total
+    Bytes(u64),
+    /// Nothing, the step just takes a while
+    Spinner,
+}
+
+/// The progress of a single step, finished when dropped.
+pub trait Progress: Send + Sync {
+    fn inc(&self, delta: u64);
+
+    fn set_position(&self, position: u64);
+
+    /// Describes what is being worked on, e.g. the current headword.
+    fn set_message(&self, message: &str);
+}
+
+impl<R: Reporter + ?Sized> Reporter for Box<R> {
+    fn step(&self, message: &str) {
+        (**self).step(message);
+    }
+
+    fn done(&self, message: &str) {
+        (**self).done(message);
+    }
+
+    fn warn(&self, message: &str) {
+        (**self).warn(message);
+    }
+
+    fn progress(&self, kind: ProgressKind) -> Box<dyn Progress> {
+        (**self).progress(kind)
+    }
+}
+
+/// Only passes warnings on to the reporter it wraps.
+pub struct Quiet<R>(pub R);
+
+impl<R: Reporter> Reporter for Quiet<R> {
+    fn step(&self, _message: &str) {}
+
+    fn done(&self, _message: &str) {}
+
+    fn warn(&self, message: &str) {
+        self.0.warn(message);
+    }
+
+    fn progress(&self, _kind: ProgressKind) -> Box<dyn Progress> {
+        Box::new(Silent)
+    }
+}
+
+/// A reporter that discards everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Silent;
+
+impl Reporter for Silent {
+    fn step(&self, _message: &str) {}
+
+    fn done(&self, _message: &str) {}
+
+    fn warn(&self, _message: &str) {}
+
+    fn progress(&self, _kind: ProgressKind) -> Box<dyn Progress> {
+        Box::new(Silent)
+    }
+}
+
+impl Progress for Silent {
+    fn inc(&self, _delta: u64) {}
+
+    fn set_position(&self, _position: u64) {}
+
+    fn set_message(&self, _message: &str) {}
+}
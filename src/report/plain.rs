@@ -0,0 +1,51 @@
+use std::{
+    io::Write,
+    sync::{Mutex, PoisonError},
+};
+
+use super::{Progress, ProgressKind, Reporter, Silent};
+
+/// Reports one plain line per event, without colors, emoji, progress bars
+/// or cursor movement, for logs and CI.
+pub struct PlainReporter {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl PlainReporter {
+    pub fn new(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    fn write_line(&self, level: &str, message: &str) {
+        let mut writer = self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+
+        // Logging is best-effort, a closed pipe shouldn't fail a build
+        let _ = writeln!(writer, "{}: {}", level, message);
+    }
+}
+
+impl Default for PlainReporter {
+    fn default() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+impl Reporter for PlainReporter {
+    fn step(&self, message: &str) {
+        self.write_line("info", &format!("{}...", message));
+    }
+
+    fn done(&self, message: &str) {
+        self.write_line("info", message);
+    }
+
+    fn warn(&self, message: &str) {
+        self.write_line("warning", message);
+    }
+
+    fn progress(&self, _kind: ProgressKind) -> Box<dyn Progress> {
+        Box::new(Silent)
+    }
+}
@@ -0,0 +1,24 @@
+use std::sync::LazyLock;
+
+use indicatif::ProgressStyle;
+
+pub static STYLE_PROGRESS: LazyLock<ProgressStyle> = LazyLock::new(|| {
+    ProgressStyle::with_template("[{bar:40.cyan/blue}] {pos}/{len} {msg}")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+});
+
+pub static STYLE_COUNTER: LazyLock<ProgressStyle> = LazyLock::new(|| {
+    ProgressStyle::with_template("[{elapsed_precise}] {spinner} {human_pos} lines {msg}")
+        .unwrap_or_else(|_| ProgressStyle::default_bar())
+});
+
+pub static STYLE_DOWNLOAD: LazyLock<ProgressStyle> = LazyLock::new(|| {
+    ProgressStyle::with_template(
+        "[{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes} ({eta})",
+    )
+    .unwrap_or_else(|_| ProgressStyle::default_bar())
+});
+
+pub static STYLE_STEP: LazyLock<ProgressStyle> = LazyLock::new(|| {
+    ProgressStyle::with_template("{msg}").unwrap_or_else(|_| ProgressStyle::default_spinner())
+});
@@ -0,0 +1,157 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    thread::{self, ThreadId},
+    time::Duration,
+};
+
+use console::Term;
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+
+use super::{
+    Progress, ProgressKind, Reporter,
+    styles::{STYLE_COUNTER, STYLE_DOWNLOAD, STYLE_PROGRESS, STYLE_STEP},
+};
+
+/// Reports to an interactive terminal with emoji status lines and progress
+/// bars. A step's line is replaced by the line that finishes it.
+///
+/// Steps are tracked per thread, so the dictionaries of a parallel
+/// `build --manifest` each keep their own step line and progress bar.
+#[derive(Debug)]
+pub struct TermReporter {
+    term: Term,
+    multi: MultiProgress,
+    /// The line of each thread's step that hasn't finished yet
+    pending: Mutex<HashMap<ThreadId, ProgressBar>>,
+}
+
+impl TermReporter {
+    pub fn new(term: Term) -> Self {
+        let multi = MultiProgress::with_draw_target(ProgressDrawTarget::term(term.clone(), 20));
+
+        Self {
+            term,
+            multi,
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn write_line(&self, line: &str) {
+        // Status lines are best-effort, a broken terminal shouldn't fail a build
+        if self.multi.is_hidden() {
+            let _ = self.term.write_line(line);
+        } else {
+            let _ = self.multi.println(line);
+        }
+    }
+
+    /// Replaces the calling thread's pending step, if any, with `line`.
+    fn finish_step(&self, line: Option<String>) {
+        let step = self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&thread::current().id());
+
+        if let Some(step) = step {
+            step.finish_and_clear();
+
+            // A step that another one follows stays on screen like before
+            if line.is_none() {
+                self.write_line(&step.message());
+            }
+        }
+
+        if let Some(line) = line {
+            self.write_line(&line);
+        }
+    }
+}
+
+impl Default for TermReporter {
+    fn default() -> Self {
+        Self::new(Term::stdout())
+    }
+}
+
+impl Reporter for TermReporter {
+    fn step(&self, message: &str) {
+        self.finish_step(None);
+
+        let line = format!("🔄 {}...", message);
+
+        if self.multi.is_hidden() {
+            self.write_line(&line);
+            return;
+        }
+
+        let step = self.multi.add(
+            ProgressBar::new_spinner()
+                .with_style(STYLE_STEP.clone())
+                .with_message(line),
+        );
+        step.tick();
+
+        self.pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(thread::current().id(), step);
+    }
+
+    fn done(&self, message: &str) {
+        self.finish_step(Some(format!("✅ {}", message)));
+    }
+
+    fn warn(&self, message: &str) {
+        self.write_line(&format!("⚠️ {}", message));
+    }
+
+    fn progress(&self, kind: ProgressKind) -> Box<dyn Progress> {
+        let bar = match kind {
+            ProgressKind::Lines => ProgressBar::new_spinner().with_style(STYLE_COUNTER.clone()),
+            ProgressKind::Items(len) => ProgressBar::new(len).with_style(STYLE_PROGRESS.clone()),
+            ProgressKind::Bytes(len) => ProgressBar::new(len).with_style(STYLE_DOWNLOAD.clone()),
+            ProgressKind::Spinner => ProgressBar::new_spinner(),
+        };
+
+        // Keep the bar right under the step it belongs to
+        let bar = match self
+            .pending
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&thread::current().id())
+        {
+            Some(step) => self.multi.insert_after(step, bar),
+            None => self.multi.add(bar),
+        };
+
+        if kind == ProgressKind::Spinner {
+            bar.enable_steady_tick(Duration::from_millis(100));
+        }
+
+        Box::new(TermProgress(bar))
+    }
+}
+
+struct TermProgress(ProgressBar);
+
+impl Progress for TermProgress {
+    fn inc(&self, delta: u64) {
+        self.0.inc(delta);
+    }
+
+    fn set_position(&self, position: u64) {
+        self.0.set_position(position);
+    }
+
+    fn set_message(&self, message: &str) {
+        self.0.set_message(message.to_string());
+    }
+}
+
+impl Drop for TermProgress {
+    fn drop(&mut self) {
+        self.0.finish_and_clear();
+    }
+}
@@ -0,0 +1,74 @@
+use std::path::Path;
+
+use console::{Term, style};
+use indicatif::HumanBytes;
+
+use odict_convert::{
+    cache::Cache,
+    frequency::has_subtitle_frequencies,
+    processors::wiktionary::{Languages, fetch_index},
+    report::Reporter,
+};
+
+pub async fn list_wiktionary(
+    reporter: &dyn Reporter,
+    cache: &Cache,
+    input: Option<&Path>,
+    term: &Term,
+) -> anyhow::Result<()> {
+    let index = fetch_index(reporter, cache, input).await?;
+    let languages = Languages::builtin()?;
+
+    let name_width = index
+        .iter()
+        .map(|l| l.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut coded = 0;
+    let mut ranked = 0;
+
+    term.write_line(&format!(
+        "{}",
+        style(format!(
+            "{:<name_width$}  {:<5}  {:<5}  {:>10}  {:>10}  subtitles",
+            "language", "639-3", "639-1", "senses", "size"
+        ))
+        .bold()
+    ))?;
+
+    for language in &index {
+        let profile = languages
+            .code_for_kaikki(&language.name)
+            .and_then(|code| languages.profile(&code).ok());
+        let subtitles = profile
+            .as_ref()
+            .is_some_and(|profile| has_subtitle_frequencies(&profile.code));
+
+        coded += usize::from(profile.is_some());
+        ranked += usize::from(subtitles);
+
+        term.write_line(&format!(
+            "{:<name_width$}  {:<5}  {:<5}  {:>10}  {:>10}  {}",
+            language.name,
+            profile.as_ref().map_or("-", |p| p.code.as_str()),
+            profile
+                .as_ref()
+                .and_then(|p| p.iso639_1.as_deref())
+                .unwrap_or("-"),
+            language.entries.map_or("-".to_string(), |n| n.to_string()),
+            language
+                .size
+                .map_or("-".to_string(), |size| HumanBytes(size).to_string()),
+            if subtitles { "✓" } else { "" }
+        ))?;
+    }
+
+    term.write_line(&format!(
+        "📚 {} languages, {} with ISO codes, {} with OpenSubtitles frequencies",
+        index.len(),
+        coded,
+        ranked
+    ))?;
+
+    Ok(())
+}
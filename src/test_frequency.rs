@@ -1,20 +1,24 @@
 use console::Term;
 
-use crate::frequency::FrequencyMap;
+use odict_convert::{cache::Cache, frequency::FrequencyMap, report::Reporter};
 
-pub async fn test_frequency(language: &str, word: &str, term: &Term) {
+pub async fn test_frequency(
+    language: &str,
+    word: &str,
+    term: &Term,
+    reporter: &dyn Reporter,
+    cache: &Cache,
+) -> anyhow::Result<()> {
     term.write_line(&format!(
         "🔍 Testing frequency for '{}' in language '{}'",
         word, language
-    ))
-    .unwrap();
+    ))?;
 
-    match FrequencyMap::new(language, &term).await.unwrap() {
+    match FrequencyMap::new(language, reporter, cache).await? {
         Some(freq_map) => {
             match freq_map.get_frequency(word) {
                 Some(rank) => {
-                    term.write_line(&format!("✅ Word '{}' has frequency rank: {}", word, rank))
-                        .unwrap();
+                    term.write_line(&format!("✅ Word '{}' has frequency rank: {}", word, rank))?;
 
                     // Convert rank to approximate proficiency level
                     let level = match rank {
@@ -25,12 +29,10 @@ pub async fn test_frequency(language: &str, word: &str, term: &Term) {
                         5001..=8000 => "C1",
                         _ => "C2+",
                     };
-                    term.write_line(&format!("📊 Approximate proficiency level: {}", level))
-                        .unwrap();
+                    term.write_line(&format!("📊 Approximate proficiency level: {}", level))?;
                 }
                 None => {
-                    term.write_line(&format!("❌ Word '{}' not found in frequency data", word))
-                        .unwrap();
+                    term.write_line(&format!("❌ Word '{}' not found in frequency data", word))?;
                 }
             }
         }
@@ -38,10 +40,9 @@ pub async fn test_frequency(language: &str, word: &str, term: &Term) {
             term.write_line(&format!(
                 "❌ No frequency data available for language '{}'",
                 language
-            ))
-            .unwrap();
+            ))?;
         }
     }
 
-    return;
+    Ok(())
 }
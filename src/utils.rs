@@ -1,121 +1,289 @@
+//! Helpers for writing dictionaries and reading source files.
+
 use std::fs::File;
-use std::io::{Read, Write};
+use std::io::{BufRead, BufReader, Read, Write};
 use std::path::{Path, PathBuf};
-use std::time::{Duration, Instant};
+use std::time::Instant;
 
-use console::Term;
-use odict::io::DictionaryWriterOptions;
-use odict::{CompressOptions, DictionaryWriter};
+use flate2::read::GzDecoder;
+use odict::{DictionaryWriter, ID};
+use serde::Deserialize;
 use sha2::{Digest, Sha256};
+use uuid::Uuid;
 
-use crate::progress::STYLE_DOWNLOAD;
+use crate::{
+    archive,
+    error::Error,
+    report::{ProgressKind, Reporter},
+};
 
-pub fn save_dictionary(
-    term: Term,
-    dictionary: &odict::Dictionary,
-    output_path: &PathBuf,
-) -> anyhow::Result<()> {
-    if let Some(parent) = Path::new(&output_path).parent() {
-        std::fs::create_dir_all(parent).unwrap();
+/// The Brotli settings dictionaries are compressed with.
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Compression {
+    pub quality: u32,
+    pub window_size: u32,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self {
+            quality: 8,
+            window_size: 22,
+        }
     }
+}
 
-    let writer = DictionaryWriter::new();
-    let spinner = indicatif::ProgressBar::new_spinner();
+/// Returns where a dictionary is written when no output path is given.
+pub fn default_output_path(source: &str, name: &str) -> PathBuf {
+    format!("out/{}/{}.odict", source, name).into()
+}
 
-    if let Some(parent) = Path::new(&output_path).parent() {
-        std::fs::create_dir_all(parent).unwrap();
-    }
+/// Returns the ID of the dictionary built from `source` for `language`,
+/// which stays the same across builds.
+pub fn dictionary_id(source: &str, language: Option<&str>) -> anyhow::Result<ID> {
+    let name = match language {
+        Some(language) => format!("odict-convert/{}/{}", source, language),
+        None => format!("odict-convert/{}", source),
+    };
 
-    spinner.enable_steady_tick(Duration::from_millis(100));
-    spinner.set_message("Writing the dictionary to file (this might take a while)...");
+    Ok(ID::try_from(
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()).to_string(),
+    )?)
+}
 
-    let t0 = Instant::now();
+/// Encodes `dictionary` in the .odict format. Unlike `DictionaryWriter`,
+/// this gives the same bytes every time for the same dictionary.
+pub fn encode_dictionary(
+    dictionary: &odict::Dictionary,
+    compression: &Compression,
+) -> anyhow::Result<Vec<u8>> {
+    // The header (signature and format version) is taken from what odict
+    // itself writes, so it always matches the version we link against
+    let empty = DictionaryWriter::new().write_to_bytes(&odict::Dictionary::default())?;
+    let version_size = u64::from_le_bytes(empty[5..13].try_into()?) as usize;
+    let header = &empty[..13 + version_size];
 
-    let compress_options = CompressOptions::default().quality(8).window_size(22);
-    let writer_options = DictionaryWriterOptions::default().compression(compress_options);
-    writer
-        .write_to_path_with_opts(&dictionary, &output_path, writer_options)
-        .unwrap();
+    let mut compressed = Vec::new();
+    let mut compressor = brotli::CompressorWriter::new(
+        &mut compressed,
+        4096,
+        compression.quality,
+        compression.window_size,
+    );
 
-    let dt = t0.elapsed().as_secs_f32();
+    compressor.write_all(&archive::to_bytes(dictionary)?)?;
+    compressor.flush()?;
 
-    spinner.finish_and_clear();
+    drop(compressor);
 
-    term.clear_last_lines(1).unwrap();
-    term.write_line(&format!(
-        "✅ Dictionary written to {} in {dt:.2}s",
-        output_path.display()
-    ))
-    .unwrap();
+    let mut output = Vec::with_capacity(header.len() + 8 + compressed.len());
 
-    Ok(())
+    output.extend_from_slice(header);
+    output.extend_from_slice(&(compressed.len() as u64).to_le_bytes());
+    output.extend_from_slice(&compressed);
+
+    Ok(output)
 }
 
-pub async fn download_with_progress(url: &str, output_path: &PathBuf) -> anyhow::Result<Vec<u8>> {
-    let mut response = reqwest::get(url).await?;
-    let total_size = response.content_length().unwrap_or(0);
-    let pb = indicatif::ProgressBar::new(total_size);
+/// Writes `dictionary` to `output_path`, creating its parent directories.
+pub fn save_dictionary(
+    reporter: &dyn Reporter,
+    dictionary: &odict::Dictionary,
+    output_path: &Path,
+    compression: &Compression,
+) -> anyhow::Result<()> {
+    reporter.step("Writing the dictionary to file (this might take a while)");
+
+    let progress = reporter.progress(ProgressKind::Spinner);
+    let t0 = Instant::now();
 
-    pb.set_style(STYLE_DOWNLOAD.clone());
+    let write = || -> anyhow::Result<()> {
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
 
-    if !response.status().is_success() {
-        anyhow::bail!("Failed to download file: {}", response.status());
-    }
+        std::fs::write(output_path, encode_dictionary(dictionary, compression)?)?;
 
-    let total_size = response.content_length().unwrap_or(0);
+        Ok(())
+    };
 
-    // Download chunks and update progress bar
-    let mut downloaded: u64 = 0;
-    let mut content = Vec::new();
+    write().map_err(|e| Error::Write {
+        path: output_path.to_path_buf(),
+        source: e,
+    })?;
 
-    while let Some(chunk) = response.chunk().await? {
-        content.extend_from_slice(&chunk);
-        downloaded = std::cmp::min(downloaded + (chunk.len() as u64), total_size);
-        pb.set_position(downloaded);
-    }
+    let dt = t0.elapsed().as_secs_f32();
 
-    pb.finish_and_clear();
+    drop(progress);
 
-    // Cache the downloaded content
-    write_file(output_path, &content)?;
+    reporter.done(&format!(
+        "Dictionary written to {} in {dt:.2}s",
+        output_path.display()
+    ));
 
-    Ok(content)
+    Ok(())
 }
 
+/// Returns the name a download of `url` is cached under.
 pub fn hash_url(url: &str) -> String {
     let mut hasher = Sha256::new();
     hasher.update(url.as_bytes());
     format!("{:x}", hasher.finalize())
 }
 
-pub fn read_file(path: &PathBuf) -> anyhow::Result<Option<Vec<u8>>> {
-    if path.exists() {
-        let mut file = File::open(path)?;
-        let mut content = Vec::new();
-        file.read_to_end(&mut content)?;
-        return Ok(Some(content));
+/// Feeds everything `reader` yields into `hasher`.
+pub fn hash_reader(hasher: &mut Sha256, mut reader: impl Read) -> anyhow::Result<()> {
+    let mut buffer = vec![0; 64 * 1024];
+
+    loop {
+        let read = reader.read(&mut buffer)?;
+
+        if read == 0 {
+            return Ok(());
+        }
+
+        hasher.update(&buffer[..read]);
     }
+}
 
-    Ok(None)
+/// Returns the hex-encoded SHA-256 of everything `reader` yields.
+pub fn sha256_reader(reader: impl Read) -> anyhow::Result<String> {
+    let mut hasher = Sha256::new();
+    hash_reader(&mut hasher, reader)?;
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
-pub fn write_file<'a>(path: &'a PathBuf, content: &'a Vec<u8>) -> anyhow::Result<()> {
-    if let Some(parent) = path.parent() {
-        if !parent.exists() {
-            std::fs::create_dir_all(parent)?;
+/// Splits what `reader` yields into lines without decoding them, so that a
+/// line that isn't valid UTF-8 can be decoded lossily instead of failing
+/// the whole read like [`BufRead::lines`] does.
+pub fn raw_lines<R: BufRead>(mut reader: R) -> impl Iterator<Item = std::io::Result<Vec<u8>>> {
+    std::iter::from_fn(move || {
+        let mut line = vec![];
+
+        match reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with(b"\n") {
+                    line.pop();
+
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                }
+
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e)),
         }
+    })
+}
+
+/// Opens a file as a buffered reader, transparently decompressing it if it
+/// is gzip-compressed.
+pub fn open_input(path: &Path) -> anyhow::Result<Box<dyn BufRead + Send>> {
+    let mut reader = BufReader::new(File::open(path)?);
+
+    if reader.fill_buf()?.starts_with(&[0x1f, 0x8b]) {
+        Ok(Box::new(BufReader::new(GzDecoder::new(reader))))
+    } else {
+        Ok(Box::new(reader))
     }
+}
 
-    let mut file = File::create(path)?;
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
 
-    file.write_all(content)?;
+    use odict::{Dictionary, DictionaryReader};
 
-    Ok(())
-}
+    use super::*;
+
+    /// A dictionary using every part of the format, so that a change in
+    /// how any of it is laid out shows up.
+    const DICTIONARY: &str = r#"
+        <dictionary id="5e5e7c0a-6c4c-5b8e-9a3e-7b0f0a6d3f11" name="Test">
+          <entry term="dog" rank="2" see="hound">
+            <ety id="dog-1" description="From Old English docga">
+              <pronunciation kind="ipa" value="/dɒɡ/">
+                <url src="https://example.com/dog.ogg" type="audio/ogg" />
+              </pronunciation>
+              <sense pos="n">
+                <definition value="A mammal">
+                  <example value="The dog barked.">
+                    <translation lang="fra" value="Le chien a aboyé." />
+                  </example>
+                  <note value="synonym:hound" />
+                </definition>
+                <group description="Informal">
+                  <definition value="A man" />
+                  <definition value="An unattractive person" />
+                </group>
+                <form kind="plural" term="dogs"><tag>plural</tag></form>
+                <form kind="possessive" term="dog's" />
+                <translation lang="fra" value="chien" />
+                <tag>countable</tag>
+              </sense>
+              <sense pos="v">
+                <definition value="To follow closely" />
+              </sense>
+            </ety>
+            <ety id="dog-2">
+              <sense pos="adj" lemma="doggy">
+                <definition value="Of dogs" />
+              </sense>
+            </ety>
+          </entry>
+          <entry term="dogs">
+            <ety>
+              <sense pos="n" lemma="dog">
+                <definition value="plural of dog"><note value="inflection:dog" /></definition>
+              </sense>
+            </ety>
+          </entry>
+          <entry term="hound" rank="900">
+            <ety><sense pos="n"><definition value="A hunting dog" /></sense></ety>
+          </entry>
+        </dictionary>
+    "#;
+
+    #[test]
+    fn splits_raw_lines() {
+        let lines = raw_lines(&b"one\r\ntw\xffo\n\nthree"[..])
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            lines,
+            [&b"one"[..], &b"tw\xffo"[..], &b""[..], &b"three"[..]]
+        );
+    }
 
-pub fn decompress_gzip(data: &[u8]) -> anyhow::Result<Vec<u8>> {
-    let mut decoder = flate2::read::GzDecoder::new(data);
-    let mut decompressed_data = Vec::new();
-    decoder.read_to_end(&mut decompressed_data)?;
-    Ok(decompressed_data)
+    #[test]
+    fn encoded_dictionaries_read_back() {
+        let dictionary = Dictionary::from_str(DICTIONARY).unwrap();
+
+        assert_eq!(dictionary.entries.len(), 3);
+
+        let encoded = encode_dictionary(&dictionary, &Compression::default()).unwrap();
+        let read = DictionaryReader::new()
+            .read_from_bytes(&encoded)
+            .unwrap()
+            .to_dictionary()
+            .unwrap();
+
+        assert_eq!(read, dictionary);
+    }
+
+    #[test]
+    fn encoding_gives_the_same_bytes() {
+        // Every parse fills new hash sets, whose iteration order differs
+        let first = Dictionary::from_str(DICTIONARY).unwrap();
+        let second = Dictionary::from_str(DICTIONARY).unwrap();
+
+        assert_eq!(
+            encode_dictionary(&first, &Compression::default()).unwrap(),
+            encode_dictionary(&second, &Compression::default()).unwrap()
+        );
+    }
 }